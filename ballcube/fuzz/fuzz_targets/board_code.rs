@@ -0,0 +1,10 @@
+#![no_main]
+
+use ballcube::Board;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|code: u64| {
+    if let Ok(board) = Board::try_from(code) {
+        assert_eq!(u64::from(&board), code);
+    }
+});
@@ -0,0 +1,16 @@
+#![no_main]
+
+use ballcube::{Board, Compact};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|codes: (u64, u64)| {
+    let (board_code, state_code) = codes;
+    let Ok(board) = Board::try_from(board_code) else {
+        return;
+    };
+    let state = Compact::from_u64(state_code, &board);
+    if let Ok(checked) = Compact::try_from_u64(state_code, &board) {
+        assert_eq!(checked, state);
+        assert_eq!(u64::from(&checked), state_code);
+    }
+});
@@ -1,4 +1,4 @@
-use crate::Player;
+use crate::{Player, Ruleset};
 
 use super::Board;
 
@@ -65,19 +65,77 @@ pub struct BoardBuilder {
     pub gates: [Option<Gate>; 12],
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Gate types each player gets in the real game, 3 meaning no hole.
+pub const STANDARD_GATE_TYPES: [u8; 6] = [0, 0, 1, 2, 3, 3];
+
+#[derive(thiserror::Error, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BoardBuildingError {
+    #[error("Direction of the gates in layer {0} is not defined")]
     GateDirectionUndefined(u8),
+    #[error("Gate {0} is not defined")]
     GateUndefined(u8),
+    #[error("Balls are not defined")]
     BallUndefined,
+    #[error("Expected 4 gold and 4 silver balls")]
     BallCountIncorrect,
+    #[error("Expected 6 gold and 6 silver gates")]
     GateAllegianceIncorrect,
+    /// Cell index of a ball is 9 or larger.
+    #[error("Ball in cell {0} is outside of the board")]
+    BallOutOfRange(u8),
+    /// Cell holds more than one ball.
+    #[error("Cell {0} holds more than one ball")]
+    BallDuplicated(u8),
+    /// Gate has a type larger than 3.
+    #[error("Gate {0} has a type larger than 3")]
+    GateTypeOutOfRange(u8),
+    /// Gate types of the player differ from the ruleset, `STANDARD_GATE_TYPES` by default.
+    #[error("Gate types of {0:?} differ from the inventory of the rules")]
+    GateInventoryIncorrect(Player),
+}
+
+impl From<&Board> for BoardBuilder {
+    fn from(board: &Board) -> Self {
+        let mut gates = [None; 12];
+        for (id, gate) in (0_u8..).zip(gates.iter_mut()) {
+            let gate_proxy = board.layer(id / 3).gate(id % 3);
+            *gate = Some(Gate {
+                allegiance: gate_proxy.owner(),
+                topleft: gate_proxy.topleft(),
+                gatetype: gate_proxy.gatetype(),
+            });
+        }
+        Self {
+            gold_balls: board.gold_balls.to_vec(),
+            silver_balls: board.silver_balls.to_vec(),
+            gates_horizontal: board.gates_horizontal.map(Some),
+            gates,
+        }
+    }
 }
 
 impl BoardBuilder {
     /// # Errors
-    /// Will error when board is not properly defined yet
-    pub fn finalize(mut self) -> Result<Board, BoardBuildingError> {
+    /// Will error when board is not properly defined yet or breaks the rules of the game.
+    pub fn finalize(self) -> Result<Board, BoardBuildingError> {
+        self.finalize_with_ruleset(&Ruleset::default())
+    }
+
+    /// Like `finalize`, but checks the gate types against `ruleset` instead of the original game.
+    /// # Errors
+    /// Will error when board is not properly defined yet or breaks the rules of the game.
+    pub fn finalize_with_ruleset(self, ruleset: &Ruleset) -> Result<Board, BoardBuildingError> {
+        self.build(Some(ruleset))
+    }
+
+    /// Like `finalize`, but allows each player to have any gate types for experimental boards.
+    /// # Errors
+    /// Will error when board is not properly defined yet or breaks the rules of the game.
+    pub fn finalize_custom_inventory(self) -> Result<Board, BoardBuildingError> {
+        self.build(None)
+    }
+
+    fn build(mut self, ruleset: Option<&Ruleset>) -> Result<Board, BoardBuildingError> {
         let mut gates_horizontal = [false; 4];
         for (id, (g, r)) in (0_u8..).zip(
             self.gates_horizontal
@@ -99,10 +157,42 @@ impl BoardBuilder {
             }
         }
 
+        if let Some(id) = gate_type_v.iter().position(|x| *x > 3) {
+            return Err(BoardBuildingError::GateTypeOutOfRange(id as u8));
+        }
+
         if gates_silver_v.iter().filter(|x| x == &&true).count() != 6 {
             return Err(BoardBuildingError::GateAllegianceIncorrect);
         }
 
+        if let Some(rules) = ruleset {
+            for player in [Player::Gold, Player::Silver] {
+                let mut expected = rules.gate_types(player);
+                expected.sort_unstable();
+                let mut inventory = gate_type_v
+                    .iter()
+                    .zip(gates_silver_v.iter())
+                    .filter(|&(_, &silver)| silver == (player == Player::Silver))
+                    .map(|(&gatetype, _)| gatetype)
+                    .collect::<Vec<_>>();
+                inventory.sort_unstable();
+                if inventory != expected {
+                    return Err(BoardBuildingError::GateInventoryIncorrect(player));
+                }
+            }
+        }
+
+        let mut occupied = [false; 9];
+        for &ball in self.gold_balls.iter().chain(self.silver_balls.iter()) {
+            let cell = occupied
+                .get_mut(ball as usize)
+                .ok_or(BoardBuildingError::BallOutOfRange(ball))?;
+            if *cell {
+                return Err(BoardBuildingError::BallDuplicated(ball));
+            }
+            *cell = true;
+        }
+
         let (mut gates_topleft, mut gates_silver, mut gate_type) =
             ([[false; 3]; 4], [[false; 3]; 4], [[0_u8; 3]; 4]);
 
@@ -138,3 +228,85 @@ impl BoardBuilder {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{BoardBuilder, BoardBuildingError, Gate};
+    use crate::Board;
+
+    fn standard_builder() -> BoardBuilder {
+        BoardBuilder::from(&Board::random())
+    }
+
+    #[test]
+    fn validate_balls() {
+        let mut outside = standard_builder();
+        outside.gold_balls[0] = 9;
+        assert_eq!(
+            outside.finalize(),
+            Err(BoardBuildingError::BallOutOfRange(9))
+        );
+
+        let mut stacked = standard_builder();
+        let duplicate = stacked.silver_balls[0];
+        stacked.gold_balls[0] = duplicate;
+        assert_eq!(
+            stacked.finalize(),
+            Err(BoardBuildingError::BallDuplicated(duplicate))
+        );
+
+        let mut missing = standard_builder();
+        missing.silver_balls.pop();
+        assert_eq!(
+            missing.finalize(),
+            Err(BoardBuildingError::BallCountIncorrect)
+        );
+    }
+
+    #[test]
+    fn validate_gates() {
+        let mut out_of_range = standard_builder();
+        let fifth = out_of_range.gates[4].expect("Gate is defined");
+        out_of_range.gates[4] = Some(Gate {
+            gatetype: 4,
+            ..fifth
+        });
+        assert_eq!(
+            out_of_range.clone().finalize(),
+            Err(BoardBuildingError::GateTypeOutOfRange(4))
+        );
+        assert_eq!(
+            out_of_range.finalize_custom_inventory(),
+            Err(BoardBuildingError::GateTypeOutOfRange(4))
+        );
+
+        let mut uneven = standard_builder();
+        let eighth = uneven.gates[7].expect("Gate is defined");
+        let changed_type = (eighth.gatetype + 1) & 0b11;
+        uneven.gates[7] = Some(Gate {
+            gatetype: changed_type,
+            ..eighth
+        });
+        assert_eq!(
+            uneven.clone().finalize(),
+            Err(BoardBuildingError::GateInventoryIncorrect(
+                eighth.allegiance
+            ))
+        );
+        let board = uneven
+            .finalize_custom_inventory()
+            .expect("Custom inventories are allowed");
+        assert_eq!(board.layer(2).gate(1).gatetype(), changed_type);
+
+        let mut lopsided = standard_builder();
+        let first = lopsided.gates[0].expect("Gate is defined");
+        lopsided.gates[0] = Some(Gate {
+            allegiance: first.allegiance.other(),
+            ..first
+        });
+        assert_eq!(
+            lopsided.finalize_custom_inventory(),
+            Err(BoardBuildingError::GateAllegianceIncorrect)
+        );
+    }
+}
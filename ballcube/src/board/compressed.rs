@@ -1,7 +1,6 @@
-use std::fmt::Display;
-
 use deku::prelude::*;
 
+use super::builder::{BoardBuilder, BoardBuildingError};
 use crate::Board;
 
 #[derive(Debug, Clone, DekuRead, DekuWrite)]
@@ -25,12 +24,11 @@ pub struct CompressedBoard {
     gates_type: [[TwoWideInt; 3]; 4],
 }
 
-#[allow(clippy::fallible_impl_from)]
 impl From<&Board> for CompressedBoard {
     fn from(board: &Board) -> Self {
-        let empty_cell_iterator =
-            (0..9).find(|x| !board.gold_balls.contains(x) && !board.silver_balls.contains(x));
-        let empty_cell = empty_cell_iterator.unwrap();
+        // The cells hold 8 distinct balls, so the empty one is what is missing from the sum of all cells
+        let occupied_sum: u8 = board.gold_balls.iter().chain(&board.silver_balls).sum();
+        let empty_cell = 36_u8.saturating_sub(occupied_sum);
         let empty_cell_delta = board.gold_balls.iter().filter(|x| x < &&empty_cell).count() as u8;
         let range_array = [0, 1, 2, 3, 4, 5, 6, 7, 8];
         Self {
@@ -44,38 +42,23 @@ impl From<&Board> for CompressedBoard {
     }
 }
 
-#[derive(Clone, Debug, thiserror::Error)]
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+#[error("Expected 4 gold and 4 silver balls, found gold in cells {gold:?} and silver in cells {silver:?}")]
+/// Ball placement of a decoded board that does not give each player 4 balls.
 pub struct BallError {
-    silver: Vec<u8>,
-    gold: Vec<u8>,
-}
-
-impl Display for BallError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let s = format!(
-            "[{}]",
-            self.silver
-                .iter()
-                .map(std::string::ToString::to_string)
-                .collect::<Vec<_>>()
-                .join(" ")
-        );
-        let g = format!(
-            "[{}]",
-            self.gold
-                .iter()
-                .map(std::string::ToString::to_string)
-                .collect::<Vec<_>>()
-                .join(" ")
-        );
-        write!(f, "({}, {})", s, g)
-    }
+    pub silver: Vec<u8>,
+    pub gold: Vec<u8>,
 }
 
-#[derive(Debug, Clone, thiserror::Error)]
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
 pub enum IncorrectCompBoardError {
     #[error("Incorrect ball amount: {0}")]
     IncorrectBallAmount(#[from] BallError),
+    /// Index among the cells without a gold ball is 5 or larger.
+    #[error("Empty cell index {0} is out of range")]
+    EmptyCellOutOfRange(u8),
+    #[error("Decoded board is not valid: {0}")]
+    InvalidBoard(#[from] BoardBuildingError),
 }
 
 impl TryFrom<CompressedBoard> for Board {
@@ -86,6 +69,11 @@ impl TryFrom<CompressedBoard> for Board {
             .filter(|x| compboard.gold_balls[*x as usize].0)
             .collect::<Vec<_>>();
 
+        if compboard.empty_cell_index >= 5 {
+            return Err(IncorrectCompBoardError::EmptyCellOutOfRange(
+                compboard.empty_cell_index,
+            ));
+        }
         let empty_cell_index =
             gold_ball_indices
                 .iter()
@@ -110,7 +98,7 @@ impl TryFrom<CompressedBoard> for Board {
             silver: silver_ball_indices.clone(),
             gold: gold_ball_indices.clone(),
         };
-        Ok(Self {
+        let board = Self {
             gold_balls: gold_ball_indices
                 .try_into()
                 .map_err(|_x| ball_error.clone())?,
@@ -121,6 +109,8 @@ impl TryFrom<CompressedBoard> for Board {
             gates_topleft,
             gates_silver,
             gate_type,
-        })
+        };
+        BoardBuilder::from(&board).finalize_custom_inventory()?;
+        Ok(board)
     }
 }
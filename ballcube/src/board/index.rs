@@ -0,0 +1,325 @@
+//! Dense numbering of all boards with the standard gate inventory.
+//!
+//! Every board is split into independent parts, each ranked on its own and combined in mixed radix:
+//! ball placement, layer directions, gate allegiance, pull sides and the gate types of both players.
+//! Placements with repeated items (balls, allegiance, gate types) are ranked as multiset permutations.
+
+use crate::{Player, Symmetry};
+
+use super::{builder::STANDARD_GATE_TYPES, Board};
+
+/// Symbols used to rank the ball placement, cell by cell.
+const BALL_COUNTS: [usize; 4] = [4, 4, 1, 0];
+const GOLD_BALL: usize = 0;
+const SILVER_BALL: usize = 1;
+const EMPTY_CELL: usize = 2;
+
+/// Symbols used to rank the gate allegiance, gate by gate.
+const ALLEGIANCE_COUNTS: [usize; 4] = [6, 6, 0, 0];
+
+const fn factorial(n: usize) -> u64 {
+    let mut result = 1;
+    let mut i = 2;
+    while i <= n {
+        result *= i as u64;
+        i += 1;
+    }
+    result
+}
+
+/// Number of distinct sequences using symbol `i` exactly `counts[i]` times.
+const fn permutation_count(counts: [usize; 4]) -> u64 {
+    let total = counts[0] + counts[1] + counts[2] + counts[3];
+    factorial(total)
+        / (factorial(counts[0])
+            * factorial(counts[1])
+            * factorial(counts[2])
+            * factorial(counts[3]))
+}
+
+const fn inventory_counts(inventory: [u8; 6]) -> [usize; 4] {
+    let mut counts = [0; 4];
+    let mut i = 0;
+    while i < inventory.len() {
+        counts[inventory[i] as usize] += 1;
+        i += 1;
+    }
+    counts
+}
+
+const BALL_RADIX: u64 = permutation_count(BALL_COUNTS);
+const DIRECTION_RADIX: u64 = 1 << 4;
+const ALLEGIANCE_RADIX: u64 = permutation_count(ALLEGIANCE_COUNTS);
+const TOPLEFT_RADIX: u64 = 1 << 12;
+const GATE_TYPE_RADIX: u64 = permutation_count(inventory_counts(STANDARD_GATE_TYPES));
+
+/// Lexicographic rank of `sequence` among all arrangements of the multiset `counts`.
+fn rank(sequence: impl IntoIterator<Item = usize>, mut counts: [usize; 4]) -> u64 {
+    let mut result = 0;
+    for symbol in sequence {
+        for smaller in 0..symbol {
+            if counts[smaller] > 0 {
+                counts[smaller] -= 1;
+                result += permutation_count(counts);
+                counts[smaller] += 1;
+            }
+        }
+        counts[symbol] -= 1;
+    }
+    result
+}
+
+/// Inverse of `rank`, writes the arrangement into `sequence`.
+fn unrank(mut index: u64, mut counts: [usize; 4], sequence: &mut [usize]) {
+    for entry in sequence {
+        for symbol in 0..counts.len() {
+            if counts[symbol] == 0 {
+                continue;
+            }
+            counts[symbol] -= 1;
+            let block = permutation_count(counts);
+            if index < block {
+                *entry = symbol;
+                break;
+            }
+            index -= block;
+            counts[symbol] += 1;
+        }
+    }
+}
+
+/// Splits `index` into its lowest digit of base `radix` and the rest.
+const fn pop_digit(index: &mut u64, radix: u64) -> u64 {
+    let digit = *index % radix;
+    *index /= radix;
+    digit
+}
+
+impl Board {
+    /// Number of boards with the standard gate inventory, the exclusive upper bound of `Board::index`.
+    pub const INDEX_COUNT: u64 = BALL_RADIX
+        * DIRECTION_RADIX
+        * ALLEGIANCE_RADIX
+        * TOPLEFT_RADIX
+        * GATE_TYPE_RADIX
+        * GATE_TYPE_RADIX;
+
+    fn gate_types(&self, player: Player) -> impl Iterator<Item = usize> + '_ {
+        self.gates_silver
+            .iter()
+            .flatten()
+            .zip(self.gate_type.iter().flatten())
+            .filter(move |&(&silver, _)| silver == (player == Player::Silver))
+            .map(|(_, &gatetype)| gatetype as usize)
+    }
+
+    /// Position of this board in `0..Board::INDEX_COUNT`.
+    /// Returns `None` when the board does not use the standard gate inventory.
+    #[must_use]
+    pub fn index(&self) -> Option<u64> {
+        let standard_counts = inventory_counts(STANDARD_GATE_TYPES);
+        for player in [Player::Gold, Player::Silver] {
+            let mut counts = [0; 4];
+            for gatetype in self.gate_types(player) {
+                counts[gatetype] += 1;
+            }
+            if counts != standard_counts {
+                return None;
+            }
+        }
+
+        let balls = rank(
+            (0..9).map(|cell| match self.ball(cell) {
+                Some(Player::Gold) => GOLD_BALL,
+                Some(Player::Silver) => SILVER_BALL,
+                None => EMPTY_CELL,
+            }),
+            BALL_COUNTS,
+        );
+        let directions = self
+            .gates_horizontal
+            .iter()
+            .rev()
+            .fold(0, |acc, &horizontal| (acc << 1) | u64::from(horizontal));
+        let allegiance = rank(
+            self.gates_silver
+                .iter()
+                .flatten()
+                .map(|&silver| usize::from(silver)),
+            ALLEGIANCE_COUNTS,
+        );
+        let topleft = self
+            .gates_topleft
+            .iter()
+            .flatten()
+            .rev()
+            .fold(0, |acc, &topleft| (acc << 1) | u64::from(topleft));
+        let gold_types = rank(self.gate_types(Player::Gold), standard_counts);
+        let silver_types = rank(self.gate_types(Player::Silver), standard_counts);
+
+        let digits = [
+            (balls, BALL_RADIX),
+            (directions, DIRECTION_RADIX),
+            (allegiance, ALLEGIANCE_RADIX),
+            (topleft, TOPLEFT_RADIX),
+            (gold_types, GATE_TYPE_RADIX),
+            (silver_types, GATE_TYPE_RADIX),
+        ];
+        Some(
+            digits
+                .iter()
+                .rev()
+                .fold(0, |acc, &(digit, radix)| acc * radix + digit),
+        )
+    }
+
+    /// The board at position `index`, inverse of `Board::index`.
+    /// Returns `None` for indices of `Board::INDEX_COUNT` or larger.
+    #[must_use]
+    pub fn from_index(mut index: u64) -> Option<Self> {
+        if index >= Self::INDEX_COUNT {
+            return None;
+        }
+        let standard_counts = inventory_counts(STANDARD_GATE_TYPES);
+
+        let mut cells = [0; 9];
+        unrank(pop_digit(&mut index, BALL_RADIX), BALL_COUNTS, &mut cells);
+        let directions = pop_digit(&mut index, DIRECTION_RADIX);
+        let mut allegiance = [0; 12];
+        unrank(
+            pop_digit(&mut index, ALLEGIANCE_RADIX),
+            ALLEGIANCE_COUNTS,
+            &mut allegiance,
+        );
+        let topleft = pop_digit(&mut index, TOPLEFT_RADIX);
+        let mut gold_types = [0; 6];
+        unrank(
+            pop_digit(&mut index, GATE_TYPE_RADIX),
+            standard_counts,
+            &mut gold_types,
+        );
+        let mut silver_types = [0; 6];
+        unrank(
+            pop_digit(&mut index, GATE_TYPE_RADIX),
+            standard_counts,
+            &mut silver_types,
+        );
+
+        let mut gold_balls = [0; 4];
+        let mut silver_balls = [0; 4];
+        let (mut gold_ball, mut silver_ball) = (gold_balls.iter_mut(), silver_balls.iter_mut());
+        for (cell, symbol) in (0_u8..).zip(cells) {
+            match symbol {
+                GOLD_BALL => *gold_ball.next()? = cell,
+                SILVER_BALL => *silver_ball.next()? = cell,
+                _ => {}
+            }
+        }
+
+        let mut gates_silver = [[false; 3]; 4];
+        let mut gates_topleft = [[false; 3]; 4];
+        let mut gate_type = [[0; 3]; 4];
+        let (mut gold_type, mut silver_type) = (gold_types.iter(), silver_types.iter());
+        for (id, ((silver, topleft_gate), gatetype)) in gates_silver
+            .iter_mut()
+            .flatten()
+            .zip(gates_topleft.iter_mut().flatten())
+            .zip(gate_type.iter_mut().flatten())
+            .enumerate()
+        {
+            *silver = allegiance[id] == 1;
+            *topleft_gate = topleft & (1 << id) != 0;
+            let next_type = if *silver {
+                silver_type.next()
+            } else {
+                gold_type.next()
+            };
+            *gatetype = *next_type? as u8;
+        }
+
+        Some(Self {
+            gold_balls,
+            silver_balls,
+            gates_horizontal: [0, 1, 2, 3].map(|layer| directions & (1 << layer) != 0),
+            gates_topleft,
+            gates_silver,
+            gate_type,
+        })
+    }
+
+    /// Every board with the standard gate inventory, ordered by `Board::index`.
+    /// Use `Board::from_index` on a subrange to split the enumeration into chunks.
+    ///
+    /// # Panics
+    /// Never.
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..Self::INDEX_COUNT).map(|index| Self::from_index(index).unwrap())
+    }
+
+    /// Whether this board has the smallest index among all its rotations and reflections.
+    #[must_use]
+    pub fn is_symmetry_representative(&self) -> bool {
+        let Some(own_index) = self.index() else {
+            return false;
+        };
+        Symmetry::ALL
+            .iter()
+            .filter_map(|symmetry| symmetry.apply_board(self).index())
+            .all(|index| index >= own_index)
+    }
+
+    /// One board for every class of boards that only differ by rotation or reflection, ordered by `Board::index`.
+    pub fn all_symmetry_representatives() -> impl Iterator<Item = Self> {
+        Self::all().filter(Self::is_symmetry_representative)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Board, Symmetry};
+
+    #[test]
+    fn index_roundtrip() {
+        assert_eq!(Board::INDEX_COUNT, 630 * 16 * 924 * 4096 * 180 * 180);
+        assert_eq!(Board::from_index(Board::INDEX_COUNT), None);
+
+        for index in (0..200).chain(Board::INDEX_COUNT - 200..Board::INDEX_COUNT) {
+            let board = Board::from_index(index).expect("Index is in range");
+            assert_eq!(board.index(), Some(index));
+            assert_eq!(
+                crate::BoardBuilder::from(&board).finalize(),
+                Ok(board),
+                "Enumerated boards are legal"
+            );
+        }
+
+        for _ in 0..1000 {
+            let board = Board::random();
+            let index = board
+                .index()
+                .expect("Random boards use the standard inventory");
+            assert!(index < Board::INDEX_COUNT);
+            assert_eq!(Board::from_index(index), Some(board));
+        }
+    }
+
+    #[test]
+    fn symmetry_representatives() {
+        for _ in 0..100 {
+            let board = Board::random();
+            let representatives = Symmetry::ALL
+                .iter()
+                .map(|symmetry| symmetry.apply_board(&board))
+                .filter(Board::is_symmetry_representative)
+                .collect::<Vec<_>>();
+            assert!(!representatives.is_empty());
+            assert!(representatives
+                .windows(2)
+                .all(|pair| pair.first() == pair.last()));
+        }
+
+        assert!(Board::all_symmetry_representatives()
+            .take(10)
+            .all(|board| board.is_symmetry_representative()));
+    }
+}
@@ -1,12 +1,15 @@
-use crate::Player;
+use crate::{BoardBuildingError, Player, Ruleset};
 use deku::{DekuContainerRead, DekuContainerWrite};
 pub mod builder;
 mod compressed;
+mod index;
+pub mod notation;
 use compressed::CompressedBoard;
+pub use compressed::{BallError, IncorrectCompBoardError};
 
 use rand::Rng;
 
-use self::{builder::Gate, compressed::IncorrectCompBoardError};
+use self::builder::Gate;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Board {
@@ -19,18 +22,19 @@ pub struct Board {
     gate_type: [[u8; 3]; 4],
 }
 
-#[allow(clippy::fallible_impl_from)]
 impl From<&Board> for u64 {
     fn from(board: &Board) -> Self {
-        let compressed = CompressedBoard::from(board);
-        let bytes = compressed.to_bytes().unwrap();
-        debug_assert_eq!(bytes.len(), 8);
-        // bytes.resize(8, 0);
-        Self::from_le_bytes(bytes.try_into().unwrap())
+        CompressedBoard::from(board)
+            .to_bytes()
+            .expect("Fields of a valid board fit into their bit widths")
+            .iter()
+            .rev()
+            .fold(0, |code, byte| (code << 8) | Self::from(*byte))
     }
 }
 
-#[derive(thiserror::Error, Debug, Clone)]
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+/// Failure to decode a board from its `u64` representation.
 pub enum DeserializationError {
     #[error("Could not read bitstring")]
     IncorrectBitstring,
@@ -118,25 +122,58 @@ impl Board {
         }
     }
 
-    /// # Panics
-    /// Never
+    /// The same board with the colours of all balls and gates exchanged.
+    /// States carry no colour information, so every `Compact` of this board is also valid for the swapped one.
+    #[must_use]
+    pub fn swap_colors(&self) -> Self {
+        Self {
+            gold_balls: self.silver_balls,
+            silver_balls: self.gold_balls,
+            gates_horizontal: self.gates_horizontal,
+            gates_topleft: self.gates_topleft,
+            gates_silver: self.gates_silver.map(|layer| layer.map(|silver| !silver)),
+            gate_type: self.gate_type,
+        }
+    }
+
+    /// Random board with the standard gate inventory.
     #[must_use]
     pub fn random() -> Self {
+        Self::random_with_rng(&mut rand::thread_rng())
+    }
+
+    /// Like `random`, but draws from `rng`, so a seeded generator always yields the same board.
+    /// # Panics
+    /// Never.
+    #[must_use]
+    pub fn random_with_rng<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Self::random_with_ruleset(&Ruleset::default(), rng)
+            .expect("Standard gate types are in range")
+    }
+
+    /// Like `random_with_rng`, but hands out the gate types of `ruleset`.
+    /// # Errors
+    /// Will error with `GateTypeOutOfRange` when the ruleset has gate types larger than 3.
+    /// # Panics
+    /// Never.
+    pub fn random_with_ruleset<R: Rng + ?Sized>(
+        ruleset: &Ruleset,
+        rng: &mut R,
+    ) -> Result<Self, BoardBuildingError> {
         use rand::seq::SliceRandom;
 
         let mut balls = (0_u8..9).collect::<Vec<_>>();
-        balls.shuffle(&mut rand::thread_rng());
+        balls.shuffle(rng);
 
         let gold_balls = balls[0..4].to_vec();
         let silver_balls = balls[4..8].to_vec();
-        let gate_types = [0_u8, 0, 1, 2, 3, 3];
-        let mut gold_gates = gate_types.to_vec();
-        let mut silver_gates = gate_types.to_vec();
+        let mut gold_gates = ruleset.gold_gate_types.to_vec();
+        let mut silver_gates = ruleset.silver_gate_types.to_vec();
         let mut gate_distribution = vec![false; 6];
         gate_distribution.extend(vec![true; 6]);
-        gold_gates.shuffle(&mut rand::thread_rng());
-        silver_gates.shuffle(&mut rand::thread_rng());
-        gate_distribution.shuffle(&mut rand::thread_rng());
+        gold_gates.shuffle(rng);
+        silver_gates.shuffle(rng);
+        gate_distribution.shuffle(rng);
 
         let gates_vec = gate_distribution
             .into_iter()
@@ -151,13 +188,13 @@ impl Board {
                 Some(Gate {
                     allegiance: if silver { Player::Silver } else { Player::Gold },
                     gatetype: t,
-                    topleft: rand::thread_rng().gen::<bool>(),
+                    topleft: rng.gen::<bool>(),
                 })
             })
             .collect::<Vec<_>>();
         let gates: [_; 12] = gates_vec.try_into().unwrap();
         let mut gates_horizontal = [true; 4];
-        rand::thread_rng().fill(&mut gates_horizontal);
+        rng.fill(&mut gates_horizontal);
         let gates_horizontal_option: [Option<bool>; 4] = gates_horizontal.map(Some);
         crate::BoardBuilder {
             gold_balls,
@@ -165,13 +202,13 @@ impl Board {
             gates_horizontal: gates_horizontal_option,
             gates,
         }
-        .finalize()
-        .unwrap()
+        .finalize_with_ruleset(ruleset)
     }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::board::notation::example;
     #[test]
     #[allow(clippy::expect_used)]
     fn board_serialize() {
@@ -184,4 +221,78 @@ mod test {
             assert_eq!(board, deserialized_board);
         }
     }
+
+    #[test]
+    fn decode_any_code() {
+        use rand::Rng as _;
+
+        let board = example::board();
+        assert_eq!(u64::from(&board), 3_660_641_897_370_290_885);
+
+        let mut rng = rand::thread_rng();
+        let mut decoded = 0;
+        for _ in 0..10000 {
+            let code: u64 = rng.gen();
+            if let Ok(decoded_board) = crate::Board::try_from(code) {
+                assert_eq!(u64::from(&decoded_board), code);
+                decoded += 1;
+            }
+        }
+        assert!(decoded > 0);
+        assert!(decoded < 10000);
+    }
+
+    #[test]
+    fn seeded_random() {
+        use crate::{Compact, Player};
+        use rand::{rngs::StdRng, SeedableRng as _};
+
+        let generate = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let board = crate::Board::random_with_rng(&mut rng);
+            let game = Compact::build_from_board(&board).random_game_with_rng(
+                &board,
+                Player::Gold,
+                &mut rng,
+            );
+            (board, game)
+        };
+        assert_eq!(generate(42), generate(42));
+        assert_ne!(generate(42), generate(43));
+    }
+
+    #[test]
+    fn swap_colors() {
+        use crate::{Compact, Player, WinningChecker};
+
+        for _ in 0..100 {
+            let board = crate::Board::random();
+            let swapped = board.swap_colors();
+            assert_eq!(swapped.swap_colors(), board);
+
+            let initial_state = Compact::build_from_board(&board);
+            assert_eq!(Compact::build_from_board(&swapped), initial_state);
+
+            let checker = WinningChecker::new(&board);
+            let swapped_checker = WinningChecker::new(&swapped);
+            for (state, played) in initial_state.random_game(&board, Player::Gold) {
+                assert_eq!(
+                    swapped.layer(played.layer()).gate(played.gate()).owner(),
+                    board
+                        .layer(played.layer())
+                        .gate(played.gate())
+                        .owner()
+                        .other()
+                );
+                assert_eq!(
+                    state.side_to_move(&swapped, Player::Silver),
+                    state.side_to_move(&board, Player::Gold).map(Player::other)
+                );
+                assert_eq!(
+                    swapped_checker.won(&state),
+                    checker.won(&state).swap_colors()
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,380 @@
+//! Text notation for boards, in the spirit of FEN in chess.
+//!
+//! A board is written as five whitespace separated tokens, for example
+//! `GG./SSG/SGS h:gt0,sb3,st1 v:gb2,gt3,sb0 h:st3,gt1,sb0 v:gb3,gt0,sb2`.
+//!
+//! The first token lists the balls row by row from the top, with rows separated by `/`.
+//! Each cell is `G` for a gold ball, `S` for a silver ball or `.` for the empty cell.
+//!
+//! The remaining four tokens describe the layers from top to bottom.
+//! A layer starts with `h:` when its gates are rows or with `v:` when they are columns,
+//! followed by its three gates, separated by `,`, from the top or left.
+//! Each gate is written as its owner (`g` or `s`), the side it is pulled from
+//! (`t` for top or left, `b` for bottom or right) and its type (`0` to `3`, 3 meaning no hole).
+//! Any gate types are accepted, so boards of every ruleset can be written down.
+
+use core::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use crate::Player;
+
+use super::builder::{BoardBuilder, BoardBuildingError, Gate};
+use super::Board;
+
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+pub enum BoardParseErrorKind {
+    #[error("Missing {0}")]
+    MissingToken(&'static str),
+    #[error("Unexpected trailing token")]
+    TrailingToken,
+    #[error("Expected 3 rows of balls separated by `/`")]
+    BallRowCount,
+    #[error("Expected 3 cells in a row of balls")]
+    BallRowLength,
+    #[error("Expected `G`, `S` or `.` for a cell, found `{0}`")]
+    InvalidBall(char),
+    #[error("Expected a layer to start with `h:` or `v:`")]
+    InvalidDirection,
+    #[error("Expected 3 gates separated by `,`")]
+    GateCount,
+    #[error("Expected gate made of owner, side and type, like `gt0`")]
+    GateLength,
+    #[error("Expected `g` or `s` for a gate owner, found `{0}`")]
+    InvalidOwner(char),
+    #[error("Expected `t` or `b` for a gate side, found `{0}`")]
+    InvalidSide(char),
+    #[error("Expected a gate type from `0` to `3`, found `{0}`")]
+    InvalidGateType(char),
+    #[error("Board is not valid: {0}")]
+    InvalidBoard(#[from] BoardBuildingError),
+}
+
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+/// Failure to read a board notation, pointing at the offending part of the input.
+pub struct BoardParseError {
+    /// Column of the offending token, counting characters from 1.
+    /// `None` when the notation is readable but the board breaks the rules of the game.
+    pub column: Option<usize>,
+    /// Offending token, empty when there is no column.
+    pub token: String,
+    pub kind: BoardParseErrorKind,
+}
+
+impl Display for BoardParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.column {
+            Some(column) => write!(f, "{} at column {column}: `{}`", self.kind, self.token),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+impl BoardParseError {
+    /// Error at the token starting `position` characters into the input.
+    fn new(position: usize, token: &str, kind: BoardParseErrorKind) -> Self {
+        Self {
+            column: Some(position + 1),
+            token: token.to_owned(),
+            kind,
+        }
+    }
+}
+
+/// Splits `text` at `separator`, remembering the position in characters of every part.
+fn split_with_positions(position: usize, text: &str, separator: char) -> Vec<(usize, &str)> {
+    let mut part_position = position;
+    text.split(separator)
+        .map(|part| {
+            let located = (part_position, part);
+            part_position += part.chars().count() + 1;
+            located
+        })
+        .collect()
+}
+
+/// Splits `text` at whitespace, remembering the position in characters of every token.
+fn tokens(text: &str) -> Vec<(usize, &str)> {
+    let mut token_position = 0;
+    text.split(char::is_whitespace)
+        .filter_map(|token| {
+            let located = (token_position, token);
+            token_position += token.chars().count() + 1;
+            (!token.is_empty()).then_some(located)
+        })
+        .collect()
+}
+
+fn parse_balls(
+    position: usize,
+    token: &str,
+    builder: &mut BoardBuilder,
+) -> Result<(), BoardParseError> {
+    let rows = split_with_positions(position, token, '/');
+    if rows.len() != 3 {
+        return Err(BoardParseError::new(
+            position,
+            token,
+            BoardParseErrorKind::BallRowCount,
+        ));
+    }
+    for (row_id, (row_position, row)) in (0_u8..).zip(rows) {
+        if row.chars().count() != 3 {
+            return Err(BoardParseError::new(
+                row_position,
+                row,
+                BoardParseErrorKind::BallRowLength,
+            ));
+        }
+        for (column_id, symbol) in (0_u8..).zip(row.chars()) {
+            let cell = row_id * 3 + column_id;
+            match symbol {
+                'G' => builder.gold_balls.push(cell),
+                'S' => builder.silver_balls.push(cell),
+                '.' => (),
+                _ => {
+                    return Err(BoardParseError::new(
+                        row_position + usize::from(column_id),
+                        &symbol.to_string(),
+                        BoardParseErrorKind::InvalidBall(symbol),
+                    ))
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_gate(position: usize, token: &str) -> Result<Gate, BoardParseError> {
+    let error = |kind| BoardParseError::new(position, token, kind);
+    let chars = token.chars().collect::<Vec<_>>();
+    let [owner, side, digit] = chars[..] else {
+        return Err(error(BoardParseErrorKind::GateLength));
+    };
+    let allegiance = match owner {
+        'g' => Player::Gold,
+        's' => Player::Silver,
+        _ => return Err(error(BoardParseErrorKind::InvalidOwner(owner))),
+    };
+    let topleft = match side {
+        't' => true,
+        'b' => false,
+        _ => return Err(error(BoardParseErrorKind::InvalidSide(side))),
+    };
+    let gatetype = match digit.to_digit(10) {
+        Some(value @ 0..=3) => value as u8,
+        _ => return Err(error(BoardParseErrorKind::InvalidGateType(digit))),
+    };
+    Ok(Gate {
+        allegiance,
+        topleft,
+        gatetype,
+    })
+}
+
+fn parse_layer(
+    position: usize,
+    token: &str,
+    layer_id: u8,
+    builder: &mut BoardBuilder,
+) -> Result<(), BoardParseError> {
+    let (horizontal, list) = if let Some(list) = token.strip_prefix("h:") {
+        (true, list)
+    } else if let Some(list) = token.strip_prefix("v:") {
+        (false, list)
+    } else {
+        return Err(BoardParseError::new(
+            position,
+            token,
+            BoardParseErrorKind::InvalidDirection,
+        ));
+    };
+    builder.gates_horizontal[layer_id as usize] = Some(horizontal);
+
+    let gates = split_with_positions(position + 2, list, ',');
+    if gates.len() != 3 {
+        return Err(BoardParseError::new(
+            position,
+            token,
+            BoardParseErrorKind::GateCount,
+        ));
+    }
+    for (gate_id, (gate_position, gate)) in (0_u8..).zip(gates) {
+        builder.gates[(layer_id * 3 + gate_id) as usize] = Some(parse_gate(gate_position, gate)?);
+    }
+    Ok(())
+}
+
+impl FromStr for Board {
+    type Err = BoardParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut builder = BoardBuilder::default();
+        let mut tokens = tokens(s).into_iter();
+
+        let end = s.chars().count();
+        let (balls_position, balls) = tokens.next().ok_or_else(|| {
+            BoardParseError::new(end, "", BoardParseErrorKind::MissingToken("balls"))
+        })?;
+        parse_balls(balls_position, balls, &mut builder)?;
+
+        for layer_id in 0..4 {
+            let (layer_position, layer) = tokens.next().ok_or_else(|| {
+                BoardParseError::new(end, "", BoardParseErrorKind::MissingToken("layer"))
+            })?;
+            parse_layer(layer_position, layer, layer_id, &mut builder)?;
+        }
+
+        if let Some((trailing_position, token)) = tokens.next() {
+            return Err(BoardParseError::new(
+                trailing_position,
+                token,
+                BoardParseErrorKind::TrailingToken,
+            ));
+        }
+
+        builder
+            .finalize_custom_inventory()
+            .map_err(|error| BoardParseError {
+                column: None,
+                token: String::new(),
+                kind: BoardParseErrorKind::InvalidBoard(error),
+            })
+    }
+}
+
+/// Example board shared by the tests.
+#[cfg(test)]
+pub mod example {
+    use crate::Board;
+
+    /// Notation of the example board.
+    pub const NOTATION: &str =
+        "GG./SSG/SGS h:gt0,sb3,st1 v:gb2,gt3,sb0 h:st3,gt1,sb0 v:gb3,gt0,sb2";
+
+    /// The board written as `NOTATION`.
+    pub fn board() -> Board {
+        NOTATION.parse().expect("Board notation is valid")
+    }
+}
+
+impl Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = (0..3)
+            .map(|row| {
+                (0..3)
+                    .map(|column| match self.ball(row * 3 + column) {
+                        Some(Player::Gold) => 'G',
+                        Some(Player::Silver) => 'S',
+                        None => '.',
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        write!(f, "{}", rows.join("/"))?;
+
+        for layer_id in 0..4 {
+            let layer = self.layer(layer_id);
+            let gates = (0..3)
+                .map(|gate_id| {
+                    let gate = layer.gate(gate_id);
+                    format!(
+                        "{}{}{}",
+                        match gate.owner() {
+                            Player::Gold => 'g',
+                            Player::Silver => 's',
+                        },
+                        if gate.topleft() { 't' } else { 'b' },
+                        gate.gatetype()
+                    )
+                })
+                .collect::<Vec<_>>();
+            write!(
+                f,
+                " {}:{}",
+                if layer.horizontal() { 'h' } else { 'v' },
+                gates.join(",")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BoardParseError, BoardParseErrorKind};
+    use crate::{Board, BoardBuildingError};
+
+    #[test]
+    fn notation_roundtrip() {
+        for _ in 0..100 {
+            let board = Board::random();
+            let notation = board.to_string();
+            assert_eq!(notation.parse::<Board>(), Ok(board));
+        }
+    }
+
+    #[test]
+    fn notation_errors() {
+        let error = |column: usize, token: &str, kind| BoardParseError {
+            column: Some(column),
+            token: token.to_owned(),
+            kind,
+        };
+        assert_eq!(
+            super::example::NOTATION
+                .parse::<Board>()
+                .map(|board| board.to_string()),
+            Ok(super::example::NOTATION.to_owned())
+        );
+
+        assert_eq!(
+            "GG./SXG/SGS h:gt0,sb3,st1 v:gb2,gt3,sb0 h:st3,gt1,sb0 v:gb3,gt0,sb2".parse::<Board>(),
+            Err(error(6, "X", BoardParseErrorKind::InvalidBall('X')))
+        );
+        assert_eq!(
+            "GG./SSG/SGS h:gt0,sb3,st1 v:gb2,gt3,sb0 h:st3,gx1,sb0 v:gb3,gt0,sb2".parse::<Board>(),
+            Err(error(47, "gx1", BoardParseErrorKind::InvalidSide('x')))
+        );
+        assert_eq!(
+            format!("{} x", super::example::NOTATION).parse::<Board>(),
+            Err(error(69, "x", BoardParseErrorKind::TrailingToken))
+        );
+        assert_eq!(
+            "GG./SSG/SGS h:gt0,sb3,st1 d:gb2,gt3,sb0".parse::<Board>(),
+            Err(error(
+                27,
+                "d:gb2,gt3,sb0",
+                BoardParseErrorKind::InvalidDirection
+            ))
+        );
+        assert_eq!(
+            "GG./SSG/SGS h:gt0,sb3,st1 v:gb2,gt3,sb0".parse::<Board>(),
+            Err(error(40, "", BoardParseErrorKind::MissingToken("layer")))
+        );
+        let wrong_allegiance =
+            "GG./SSG/SGS h:gt0,sb3,st1 v:gb2,gt3,sb0 h:st3,gt1,sb0 v:gb3,st0,sb2";
+        let allegiance_error = wrong_allegiance
+            .parse::<Board>()
+            .expect_err("Silver has seven gates");
+        assert_eq!(
+            allegiance_error,
+            BoardParseError {
+                column: None,
+                token: String::new(),
+                kind: BoardParseErrorKind::InvalidBoard(
+                    BoardBuildingError::GateAllegianceIncorrect
+                )
+            }
+        );
+        assert!(!allegiance_error.to_string().contains("column"));
+
+        // Columns count characters, the non-breaking space takes two bytes
+        assert_eq!(
+            "GG./SSG/SGS\u{a0}h:gt0,sb3,st1 v:gb2,gt3,sb0 h:st3,gx1,sb0 v:gb3,gt0,sb2"
+                .parse::<Board>(),
+            Err(error(47, "gx1", BoardParseErrorKind::InvalidSide('x')))
+        );
+    }
+}
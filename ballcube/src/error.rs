@@ -0,0 +1,86 @@
+use crate::{
+    BoardBuildingError, BoardParseError, DeserializationError, DiagramParseError,
+    EndConditionParseError, GateTypeError, MoveError, MoveParseError, NoMovesRuleParseError,
+    RecordParseError, RulesetParseError, StateDecodingError, TurnError,
+};
+
+/// Any failure of the crate, for callers that report errors without handling each kind separately.
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+#[expect(
+    clippy::error_impl_error,
+    reason = "Callers name it `ballcube::Error`, like `std::io::Error`"
+)]
+pub enum Error {
+    #[error("Invalid board: {0}")]
+    BoardBuilding(#[from] BoardBuildingError),
+    #[error("Invalid board notation: {0}")]
+    BoardParse(#[from] BoardParseError),
+    #[error("Could not decode board: {0}")]
+    BoardDecoding(#[from] DeserializationError),
+    #[error("Could not decode state: {0}")]
+    StateDecoding(#[from] StateDecodingError),
+    #[error("Invalid state diagram: {0}")]
+    DiagramParse(#[from] DiagramParseError),
+    #[error("Invalid move notation: {0}")]
+    MoveParse(#[from] MoveParseError),
+    #[error("Illegal move: {0}")]
+    Move(#[from] MoveError),
+    #[error("Invalid turn: {0}")]
+    Turn(#[from] TurnError),
+    #[error("Invalid game record: {0}")]
+    RecordParse(#[from] RecordParseError),
+    #[error("Invalid rules: {0}")]
+    EndConditionParse(#[from] EndConditionParseError),
+    #[error("Invalid rules: {0}")]
+    NoMovesRuleParse(#[from] NoMovesRuleParseError),
+    #[error("Invalid rules: {0}")]
+    RulesetParse(#[from] RulesetParseError),
+    #[error("Invalid rules: {0}")]
+    GateType(#[from] GateTypeError),
+}
+
+#[cfg(test)]
+mod test {
+    use super::Error;
+    use crate::board::notation::example;
+    use crate::{BoardBuilder, DeserializationError, Game, Move, Player};
+
+    fn play(board: &str, moves: &[&str]) -> Result<Game, Error> {
+        let mut game = Game::new(board.parse()?, Player::Gold);
+        for notation in moves {
+            game.apply(notation.parse::<Move>()?)?;
+        }
+        Ok(game)
+    }
+
+    #[test]
+    fn readable_messages() {
+        let board = example::NOTATION;
+        play(board, &["1a", "1b"]).expect("Moves are legal");
+        assert_eq!(
+            play(board, &["1b"])
+                .map(|_| ())
+                .map_err(|error| error.to_string()),
+            Err("Illegal move: Gate 1b does not belong to Gold".to_owned())
+        );
+        assert_eq!(
+            play(board, &["5a"])
+                .map(|_| ())
+                .map_err(|error| error.to_string()),
+            Err("Invalid move notation: Expected a layer from 1 to 4, found `5`".to_owned())
+        );
+        assert_eq!(
+            Error::from(
+                BoardBuilder::default()
+                    .finalize()
+                    .expect_err("Builder is empty")
+            )
+            .to_string(),
+            "Invalid board: Direction of the gates in layer 0 is not defined"
+        );
+        assert_eq!(
+            Error::from(DeserializationError::IncorrectBitstring).to_string(),
+            "Could not decode board: Could not read bitstring"
+        );
+    }
+}
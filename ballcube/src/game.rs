@@ -0,0 +1,281 @@
+use crate::{
+    Board, Compact, Move, MoveChecker, MoveError, NoMovesRule, Player, Ruleset, ShiftRecord,
+    Winner, WinningChecker,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A game on a fixed board, remembering every move played since the start.
+pub struct Game {
+    board: Board,
+    starting_player: Player,
+    ruleset: Ruleset,
+    state: Compact,
+    moves: Vec<Move>,
+    records: Vec<ShiftRecord>,
+}
+
+impl Game {
+    #[must_use]
+    pub fn new(board: Board, starting_player: Player) -> Self {
+        Self::with_ruleset(board, starting_player, Ruleset::default())
+    }
+
+    /// Starts a game played with variations of the rules.
+    #[must_use]
+    pub fn with_ruleset(board: Board, starting_player: Player, ruleset: Ruleset) -> Self {
+        let state = Compact::build_from_board(&board);
+        Self {
+            board,
+            starting_player,
+            ruleset,
+            state,
+            moves: vec![],
+            records: vec![],
+        }
+    }
+
+    /// Starts a new game and plays all `moves` in order.
+    /// # Errors
+    /// Returns the error of the first illegal move.
+    pub fn from_moves<I: IntoIterator<Item = Move>>(
+        board: Board,
+        starting_player: Player,
+        moves: I,
+    ) -> Result<Self, MoveError> {
+        Self::from_moves_with_ruleset(board, starting_player, Ruleset::default(), moves)
+    }
+
+    /// Like `from_moves`, but plays with variations of the rules.
+    /// # Errors
+    /// Returns the error of the first illegal move.
+    pub fn from_moves_with_ruleset<I: IntoIterator<Item = Move>>(
+        board: Board,
+        starting_player: Player,
+        ruleset: Ruleset,
+        moves: I,
+    ) -> Result<Self, MoveError> {
+        let mut game = Self::with_ruleset(board, starting_player, ruleset);
+        for next_move in moves {
+            game.apply(next_move)?;
+        }
+        Ok(game)
+    }
+
+    #[must_use]
+    pub const fn board(&self) -> &Board {
+        &self.board
+    }
+
+    #[must_use]
+    pub const fn starting_player(&self) -> Player {
+        self.starting_player
+    }
+
+    #[must_use]
+    pub const fn ruleset(&self) -> &Ruleset {
+        &self.ruleset
+    }
+
+    #[must_use]
+    pub const fn state(&self) -> &Compact {
+        &self.state
+    }
+
+    /// All moves played so far, oldest first.
+    #[must_use]
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    /// Number of moves played so far.
+    #[must_use]
+    pub const fn ply(&self) -> usize {
+        self.moves.len()
+    }
+
+    /// Player whose turn it is, the other player moves instead while this one has to pass.
+    #[must_use]
+    pub fn current_player(&self) -> Player {
+        let player = if self.ply().is_multiple_of(2) {
+            self.starting_player
+        } else {
+            self.starting_player.other()
+        };
+        if self.ruleset.no_moves == NoMovesRule::Pass
+            && MoveChecker::new(&self.board)
+                .legal_moves(&self.state, player)
+                .is_empty()
+        {
+            player.other()
+        } else {
+            player
+        }
+    }
+
+    #[must_use]
+    pub fn winner(&self) -> Winner {
+        WinningChecker::with_ruleset(&self.board, &self.ruleset)
+            .outcome(&self.state, self.current_player())
+    }
+
+    #[must_use]
+    pub fn is_over(&self) -> bool {
+        self.winner() != Winner::None
+    }
+
+    /// Moves the current player may play, empty when the game is over.
+    #[must_use]
+    pub fn legal_moves(&self) -> Vec<Move> {
+        if self.is_over() {
+            return vec![];
+        }
+        MoveChecker::new(&self.board).moves(&self.state, self.current_player())
+    }
+
+    /// Plays a move for the current player.
+    /// # Errors
+    /// Will error when the move is not legal in the current position, the game is left untouched then.
+    pub fn apply(&mut self, next_move: Move) -> Result<(), MoveError> {
+        if self.is_over() {
+            return Err(MoveError::GameOver);
+        }
+        Move::new_checked(
+            &self.board,
+            self.current_player(),
+            next_move.layer(),
+            next_move.gate(),
+        )?;
+        if self.state.get_shift(next_move.layer(), next_move.gate()) >= 3 {
+            return Err(MoveError::GateExhausted {
+                layer: next_move.layer(),
+                gate: next_move.gate(),
+            });
+        }
+
+        let record = self
+            .state
+            .shift_gate(&self.board, next_move.layer(), next_move.gate());
+        self.moves.push(next_move);
+        self.records.push(record);
+        Ok(())
+    }
+
+    /// Takes back the last move, returning it.
+    pub fn undo(&mut self) -> Option<Move> {
+        let record = self.records.pop()?;
+        self.state.unshift_gate(&self.board, record);
+        self.moves.pop()
+    }
+
+    /// The same game, replayed from the start up to and including move number `ply`.
+    /// Returns `None` if fewer moves have been played.
+    /// # Panics
+    /// Never.
+    #[must_use]
+    pub fn replay_to(&self, ply: usize) -> Option<Self> {
+        let moves = self.moves.get(..ply)?;
+        let mut game = Self::with_ruleset(
+            self.board.clone(),
+            self.starting_player,
+            self.ruleset.clone(),
+        );
+        for &next_move in moves {
+            game.apply(next_move)
+                .expect("Moves were legal when they were next_move");
+        }
+        Some(game)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::cmp::Ordering;
+
+    use super::Game;
+    use crate::{Board, EndCondition, MoveError, Player, Ruleset, Winner, WinningChecker};
+
+    #[test]
+    fn apply_undo_replay() {
+        use rand::seq::SliceRandom as _;
+
+        for _ in 0..100 {
+            let mut game = Game::new(Board::random(), Player::Gold);
+            let mut states = vec![*game.state()];
+            while let Some(&next_move) = game.legal_moves().choose(&mut rand::thread_rng()) {
+                assert_eq!(
+                    game.board()
+                        .layer(next_move.layer())
+                        .gate(next_move.gate())
+                        .owner(),
+                    game.current_player()
+                );
+                game.apply(next_move).expect("Legal move was rejected");
+                states.push(*game.state());
+            }
+            assert_ne!(game.winner(), Winner::None);
+            assert_eq!(game.apply(game.moves()[0]), Err(MoveError::GameOver));
+
+            for (ply, state) in states.iter().enumerate() {
+                let replayed = game.replay_to(ply).expect("Ply is part of the game");
+                assert_eq!(replayed.state(), state);
+                assert_eq!(replayed.moves(), &game.moves()[..ply]);
+            }
+            assert!(game.replay_to(states.len()).is_none());
+
+            while game.undo().is_some() {
+                assert_eq!(game.state(), &states[game.ply()]);
+            }
+            assert_eq!(game.ply(), 0);
+        }
+    }
+
+    #[test]
+    fn reject_foreign_gate() {
+        let mut game = Game::new(Board::random(), Player::Silver);
+        let first = game.legal_moves()[0];
+        game.apply(first).expect("Legal move was rejected");
+        assert!(matches!(
+            game.apply(first),
+            Err(MoveError::WrongOwner {
+                player: Player::Gold,
+                ..
+            })
+        ));
+        assert_eq!(game.ply(), 1);
+    }
+
+    #[test]
+    fn house_rules() {
+        use rand::seq::SliceRandom as _;
+
+        for end_condition in [EndCondition::BallsOut(2), EndCondition::ShiftLimit(10)] {
+            let ruleset = Ruleset {
+                end_condition,
+                ..Ruleset::default()
+            };
+            for _ in 0..100 {
+                let mut game = Game::with_ruleset(Board::random(), Player::Gold, ruleset.clone());
+                while let Some(&next_move) = game.legal_moves().choose(&mut rand::thread_rng()) {
+                    game.apply(next_move).expect("Legal move was rejected");
+                }
+                let checker = WinningChecker::with_ruleset(game.board(), &ruleset);
+                let gold = checker.balls_out(game.state(), Player::Gold);
+                let silver = checker.balls_out(game.state(), Player::Silver);
+                let condition_met = match end_condition {
+                    EndCondition::BallsOut(count) => gold.max(silver) >= count,
+                    EndCondition::ShiftLimit(count) => game.ply() <= count.into(),
+                    EndCondition::AllBallsOut => false,
+                };
+                assert!(condition_met, "Game ended before {end_condition}");
+                let expected = match gold.cmp(&silver) {
+                    Ordering::Greater => Winner::One(Player::Gold),
+                    Ordering::Less => Winner::One(Player::Silver),
+                    Ordering::Equal => Winner::Both,
+                };
+                assert_eq!(game.winner(), expected);
+                let replayed = game.replay_to(game.ply()).expect("Ply is part of the game");
+                assert_eq!(replayed.winner(), expected);
+            }
+        }
+    }
+}
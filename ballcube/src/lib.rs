@@ -22,22 +22,76 @@
     clippy::expect_used,
     clippy::unwrap_used
 )]
+#![expect(
+    clippy::absolute_paths,
+    reason = "Error types derive `thiserror::Error` by its path"
+)]
+#![expect(
+    clippy::arbitrary_source_item_ordering,
+    reason = "Items are grouped by topic rather than by kind"
+)]
+#![expect(
+    clippy::integer_division_remainder_used,
+    reason = "Cells are split into rows and columns with `/ 3` and `% 3`"
+)]
+#![expect(
+    clippy::missing_trait_methods,
+    reason = "Provided trait methods are good enough for these types"
+)]
+#![expect(
+    clippy::multiple_inherent_impl,
+    reason = "Methods of `Board` and `Compact` are split by topic across modules"
+)]
+#![expect(
+    clippy::pub_use,
+    reason = "The public API is re-exported from private modules"
+)]
+#![expect(clippy::question_mark_used, reason = "Errors are propagated with `?`")]
+#![expect(
+    clippy::single_call_fn,
+    reason = "Steps of longer functions are split out into helpers"
+)]
 
 mod board;
+mod error;
+mod game;
 mod move_check;
+mod parse_state;
+mod record;
+mod ruleset;
+#[cfg(feature = "serde")]
+mod serde_support;
 mod state;
+mod symmetry;
 mod visualize_state;
+mod visualize_svg;
 mod win_check;
 
-pub use move_check::{Move, MoveChecker};
+pub use error::Error;
+pub use game::Game;
+pub use move_check::{
+    ColoredMove, Move, MoveChecker, MoveError, MoveParseError, MoveSet, MoveSetIter,
+};
+pub use parse_state::{parse_state_diagram, DiagramParseError};
+pub use record::{GameRecord, RecordParseError, RecordParseErrorKind, TagError};
+pub use ruleset::{
+    EndCondition, EndConditionParseError, GateTypeError, NoMovesRule, NoMovesRuleParseError,
+    Ruleset, RulesetParseError,
+};
 pub use win_check::{Winner, WinningChecker};
 
-pub use board::builder::{BoardBuilder, BoardBuildingError};
-pub use board::Board;
-pub use state::Compact;
-pub use visualize_state::visualize_state;
+pub use board::builder::{BoardBuilder, BoardBuildingError, STANDARD_GATE_TYPES};
+pub use board::notation::{BoardParseError, BoardParseErrorKind};
+pub use board::{BallError, Board, DeserializationError, IncorrectCompBoardError};
+pub use state::{
+    BallDrop, Compact, MoveEffect, ShiftRecord, StateDecodingError, StateIndexer, TurnError,
+};
+pub use symmetry::Symmetry;
+pub use visualize_state::{visualize_state, RenderStyle, StateView};
+pub use visualize_svg::visualize_svg;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Color of balls a player controls
 pub enum Player {
     Gold,
@@ -46,7 +100,7 @@ pub enum Player {
 
 impl Player {
     #[must_use]
-    /// The player opposing current player
+    /// The player opposing current player.
     pub const fn other(self) -> Self {
         match self {
             Self::Gold => Self::Silver,
@@ -1,16 +1,144 @@
+use core::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
 use crate::{Board, Compact, Player};
 
 pub struct MoveChecker {
-    gold_gates: [Move; 6],
-    silver_gates: [Move; 6],
+    gold_gates: MoveSet,
+    silver_gates: MoveSet,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+/// Set of gates, bit `layer * 3 + gate` standing for the move shifting that gate.
+/// Iterating yields the moves ordered by layer and then by gate without allocating.
+pub struct MoveSet {
+    gates: u16,
 }
 
+impl MoveSet {
+    /// Mask with one bit for every gate on the board.
+    pub const ALL_GATES: u16 = (1 << 12) - 1;
+
+    /// Set from a mask with bit `layer * 3 + gate` for each contained move, higher bits are ignored.
+    #[must_use]
+    pub const fn from_bits(gates: u16) -> Self {
+        Self {
+            gates: gates & Self::ALL_GATES,
+        }
+    }
+
+    #[must_use]
+    pub const fn bits(self) -> u16 {
+        self.gates
+    }
+
+    #[must_use]
+    pub const fn len(self) -> usize {
+        self.gates.count_ones() as usize
+    }
+
+    #[must_use]
+    pub const fn is_empty(self) -> bool {
+        self.gates == 0
+    }
+
+    #[must_use]
+    pub const fn contains(self, gate_move: Move) -> bool {
+        self.gates & (1 << (gate_move.layer * 3 + gate_move.gate)) != 0
+    }
+
+    /// Gates that have not been pulled out completely in `state`.
+    #[must_use]
+    pub const fn unexhausted(state: &Compact) -> Self {
+        let shifts = state.get_shift_bits();
+        // A gate is exhausted when both bits of its shift count are set
+        let mut exhausted = shifts & (shifts >> 1) & 0x0055_5555;
+        exhausted = (exhausted | (exhausted >> 1)) & 0x0033_3333;
+        exhausted = (exhausted | (exhausted >> 2)) & 0x000F_0F0F;
+        exhausted = (exhausted | (exhausted >> 4)) & 0x00FF_00FF;
+        exhausted = (exhausted | (exhausted >> 8)) & 0x0000_FFFF;
+        Self::from_bits(!(exhausted as u16))
+    }
+
+    #[must_use]
+    pub const fn intersection(self, other: Self) -> Self {
+        Self {
+            gates: self.gates & other.gates,
+        }
+    }
+}
+
+impl IntoIterator for MoveSet {
+    type Item = Move;
+    type IntoIter = MoveSetIter;
+
+    fn into_iter(self) -> MoveSetIter {
+        MoveSetIter { gates: self.gates }
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Iterator over the moves of a `MoveSet`.
+pub struct MoveSetIter {
+    gates: u16,
+}
+
+impl Iterator for MoveSetIter {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        if self.gates == 0 {
+            return None;
+        }
+        let id = self.gates.trailing_zeros() as u8;
+        self.gates &= self.gates - 1;
+        Some(Move::new(id / 3, id % 3))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.gates.count_ones() as usize;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for MoveSetIter {}
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// Shift of a single gate.
+/// Written as the layer number counted from 1 at the top, followed by the gate letter `a` to `c` counted from the top or left, like `2b`.
 pub struct Move {
     layer: u8,
     gate: u8,
 }
 impl Move {
+    pub(crate) const fn new(layer: u8, gate: u8) -> Self {
+        Self { layer, gate }
+    }
+
+    /// Creates a move for `player`, checking that the gate exists and belongs to them.
+    /// # Errors
+    /// Will error when the gate does not exist or belongs to the other player.
+    pub fn new_checked(
+        board: &Board,
+        player: Player,
+        layer: u8,
+        gate: u8,
+    ) -> Result<Self, MoveError> {
+        if layer >= 4 || gate >= 3 {
+            return Err(MoveError::OutOfRange { layer, gate });
+        }
+        if board.layer(layer).gate(gate).owner() != player {
+            return Err(MoveError::WrongOwner {
+                layer,
+                gate,
+                player,
+            });
+        }
+        Ok(Self::new(layer, gate))
+    }
+
     #[must_use]
     pub const fn layer(self) -> u8 {
         self.layer
@@ -19,46 +147,245 @@ impl Move {
     pub const fn gate(self) -> u8 {
         self.gate
     }
+
+    /// The move together with the owner of its gate on `board`.
+    #[must_use]
+    pub const fn colored(self, board: &Board) -> ColoredMove {
+        ColoredMove {
+            player: board.layer(self.layer).gate(self.gate).owner(),
+            shift: self,
+        }
+    }
+}
+
+/// Move notation of a gate given by 0-based indices, like `2b` for gate 1 in layer 1.
+fn notation(layer: u8, gate: u8) -> String {
+    format!(
+        "{}{}",
+        u16::from(layer) + 1,
+        char::from(b'a'.saturating_add(gate))
+    )
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", notation(self.layer, self.gate))
+    }
+}
+
+impl FromStr for Move {
+    type Err = MoveParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        let (Some(layer_char), Some(gate_char), None) = (chars.next(), chars.next(), chars.next())
+        else {
+            return Err(MoveParseError::Length(s.to_owned()));
+        };
+        let layer = match layer_char {
+            '1'..='4' => layer_char as u8 - b'1',
+            _ => return Err(MoveParseError::InvalidLayer(layer_char)),
+        };
+        let gate = match gate_char {
+            'a'..='c' => gate_char as u8 - b'a',
+            _ => return Err(MoveParseError::InvalidGate(gate_char)),
+        };
+        Ok(Self::new(layer, gate))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// A move together with the colour of the player making it, written like `G2b` or `S4a`.
+pub struct ColoredMove {
+    pub player: Player,
+    pub shift: Move,
+}
+
+impl ColoredMove {
+    /// Checks that the gate of the move belongs to the given player on `board`.
+    /// # Errors
+    /// Will error when the gate belongs to the other player.
+    pub fn validate(self, board: &Board) -> Result<Move, MoveError> {
+        Move::new_checked(board, self.player, self.shift.layer, self.shift.gate)
+    }
+}
+
+impl Display for ColoredMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let owner = match self.player {
+            Player::Gold => 'G',
+            Player::Silver => 'S',
+        };
+        write!(f, "{}{}", owner, self.shift)
+    }
+}
+
+impl FromStr for ColoredMove {
+    type Err = MoveParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        let player = match chars.next() {
+            Some('G') => Player::Gold,
+            Some('S') => Player::Silver,
+            Some(owner) => return Err(MoveParseError::InvalidOwner(owner)),
+            None => return Err(MoveParseError::Length(s.to_owned())),
+        };
+        Ok(Self {
+            player,
+            shift: chars.as_str().parse()?,
+        })
+    }
+}
+
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+pub enum MoveParseError {
+    #[error("Expected a layer from 1 to 4 and a gate from a to c, like `2b`, found `{0}`")]
+    Length(String),
+    #[error("Expected a layer from 1 to 4, found `{0}`")]
+    InvalidLayer(char),
+    #[error("Expected a gate from a to c, found `{0}`")]
+    InvalidGate(char),
+    #[error("Expected `G` or `S` for the moving player, found `{0}`")]
+    InvalidOwner(char),
+}
+
+#[derive(thiserror::Error, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveError {
+    #[error("There is no gate {}", notation(*.layer, *.gate))]
+    OutOfRange { layer: u8, gate: u8 },
+    #[error("Gate {} does not belong to {player:?}", notation(*.layer, *.gate))]
+    WrongOwner { layer: u8, gate: u8, player: Player },
+    #[error("Gate {} is already pulled out completely", notation(*.layer, *.gate))]
+    GateExhausted { layer: u8, gate: u8 },
+    #[error("Game is already over")]
+    GameOver,
 }
 
 impl MoveChecker {
-    /// # Panics
-    /// Never
     #[must_use]
-    pub fn new(board: &Board) -> Self {
-        let mut gold_gates = vec![];
-        let mut silver_gates = vec![];
+    pub const fn new(board: &Board) -> Self {
+        let mut gold_gates = 0;
+        let mut silver_gates = 0;
 
-        for layer in 0..4 {
-            for gate in 0..3 {
-                let chosen_board = match board.layer(layer).gate(gate).owner() {
-                    Player::Gold => &mut gold_gates,
-                    Player::Silver => &mut silver_gates,
-                };
-                chosen_board.push(Move { layer, gate });
+        let mut id = 0;
+        while id < 12 {
+            match board.layer(id / 3).gate(id % 3).owner() {
+                Player::Gold => gold_gates |= 1 << id,
+                Player::Silver => silver_gates |= 1 << id,
             }
+            id += 1;
         }
 
-        debug_assert_eq!(gold_gates.len(), 6);
-        debug_assert_eq!(silver_gates.len(), 6);
-
         Self {
-            gold_gates: gold_gates.try_into().unwrap(),
-            silver_gates: silver_gates.try_into().unwrap(),
+            gold_gates: MoveSet::from_bits(gold_gates),
+            silver_gates: MoveSet::from_bits(silver_gates),
         }
     }
 
+    /// Legal moves of `player` in `state`, without allocating.
     #[must_use]
-    pub fn moves(&self, state: &Compact, p: Player) -> Vec<Move> {
-        let gates = match p {
-            Player::Gold => &self.gold_gates,
-            Player::Silver => &self.silver_gates,
+    pub const fn legal_moves(&self, state: &Compact, player: Player) -> MoveSet {
+        let gates = match player {
+            Player::Gold => self.gold_gates,
+            Player::Silver => self.silver_gates,
         };
+        gates.intersection(MoveSet::unexhausted(state))
+    }
+
+    #[must_use]
+    pub fn moves(&self, state: &Compact, player: Player) -> Vec<Move> {
+        self.legal_moves(state, player).into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ColoredMove, Move, MoveChecker, MoveError, MoveParseError, MoveSet};
+    use crate::{Board, Compact, Player};
 
-        gates
-            .iter()
-            .copied()
-            .filter(|&Move { layer, gate }| state.get_shift(layer, gate) < 3)
-            .collect()
+    #[test]
+    fn legal_move_mask() {
+        for _ in 0..100 {
+            let board = Board::random();
+            let checker = MoveChecker::new(&board);
+            let initial_state = Compact::build_from_board(&board);
+            for (state, _) in initial_state.random_game(&board, Player::Silver) {
+                for player in [Player::Gold, Player::Silver] {
+                    let expected = (0..12)
+                        .map(|id| Move::new(id / 3, id % 3))
+                        .filter(|gate_move| {
+                            board.layer(gate_move.layer).gate(gate_move.gate).owner() == player
+                        })
+                        .filter(|gate_move| state.get_shift(gate_move.layer, gate_move.gate) < 3)
+                        .collect::<Vec<_>>();
+                    let moves = checker.legal_moves(&state, player);
+                    assert_eq!(moves.len(), expected.len());
+                    assert!(expected.iter().all(|gate_move| moves.contains(*gate_move)));
+                    assert_eq!(moves.into_iter().collect::<Vec<_>>(), expected);
+                }
+            }
+        }
+        assert_eq!(MoveSet::from_bits(u16::MAX).bits(), MoveSet::ALL_GATES);
+    }
+
+    #[test]
+    fn move_notation() {
+        let board = Board::random();
+        for layer in 0..4 {
+            for gate in 0..3 {
+                let gate_move = Move::new(layer, gate);
+                assert_eq!(gate_move.to_string().parse(), Ok(gate_move));
+
+                let colored = gate_move.colored(&board);
+                assert_eq!(colored.to_string().parse(), Ok(colored));
+                assert_eq!(colored.validate(&board), Ok(gate_move));
+
+                let player = colored.player;
+                assert_eq!(
+                    Move::new_checked(&board, player, layer, gate),
+                    Ok(gate_move)
+                );
+                assert_eq!(
+                    Move::new_checked(&board, player.other(), layer, gate),
+                    Err(MoveError::WrongOwner {
+                        layer,
+                        gate,
+                        player: player.other()
+                    })
+                );
+            }
+        }
+        assert_eq!(Move::new(1, 2).to_string(), "2c");
+        assert_eq!(
+            ColoredMove {
+                player: Player::Silver,
+                shift: Move::new(3, 0)
+            }
+            .to_string(),
+            "S4a"
+        );
+        assert_eq!(
+            Move::new_checked(&board, Player::Gold, 4, 0),
+            Err(MoveError::OutOfRange { layer: 4, gate: 0 })
+        );
+        assert_eq!(
+            MoveError::OutOfRange { layer: 4, gate: 0 }.to_string(),
+            "There is no gate 5a"
+        );
+        assert_eq!(
+            MoveError::GateExhausted { layer: 3, gate: 2 }.to_string(),
+            "Gate 4c is already pulled out completely"
+        );
+        assert_eq!("5a".parse::<Move>(), Err(MoveParseError::InvalidLayer('5')));
+        assert_eq!("1d".parse::<Move>(), Err(MoveParseError::InvalidGate('d')));
+        assert_eq!(
+            "1ab".parse::<Move>(),
+            Err(MoveParseError::Length("1ab".to_owned()))
+        );
+        assert_eq!(
+            "X1a".parse::<ColoredMove>(),
+            Err(MoveParseError::InvalidOwner('X'))
+        );
     }
 }
@@ -0,0 +1,371 @@
+//! Reading the diagrams of `RenderStyle::Ascii` back into a board and a state.
+//!
+//! The diagram consists of five rows of five blocks separated by a single space,
+//! each block made of five by five fields that are three characters wide.
+//! Labels as added by `StateView::labels` and the colour codes of `RenderStyle::Ansi` are accepted as well.
+//! Since the blocks only mark a ball where it currently rests, the empty cell is the one showing no ball at all.
+
+use crate::{
+    board::builder::{BoardBuilder, BoardBuildingError, Gate},
+    state::{BallBitmask, Compact, StateDecodingError},
+    Board, Player,
+};
+
+const FIELD_WIDTH: usize = 3;
+const BLOCK_WIDTH: usize = 5 * FIELD_WIDTH + 1;
+const LABEL_WIDTH: usize = 2;
+
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+pub enum DiagramParseError {
+    #[error("Expected 5 rows of blocks, found {0}")]
+    LineCount(usize),
+    #[error("Expected {expected} at line {line}, column {column}, found `{found}`")]
+    InvalidField {
+        line: usize,
+        column: usize,
+        expected: &'static str,
+        found: String,
+    },
+    #[error("Ball in cell {0} is drawn more than once")]
+    DuplicateBall(u8),
+    #[error("Hole in cell {cell} of layer {layer} does not match the gates")]
+    HoleMismatch { layer: u8, cell: u8 },
+    #[error("Board is not valid: {0}")]
+    InvalidBoard(#[from] BoardBuildingError),
+    #[error("State is not valid: {0}")]
+    InvalidState(#[from] StateDecodingError),
+}
+
+/// Removes the escape sequences of `RenderStyle::Ansi`.
+fn strip_colors(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(symbol) = chars.next() {
+        if symbol == '\u{1b}' {
+            chars.by_ref().find(|ending| *ending == 'm');
+        } else {
+            result.push(symbol);
+        }
+    }
+    result
+}
+
+struct Grid<'text> {
+    lines: Vec<&'text str>,
+    /// Line number of the first row, counting from 1.
+    first_line: usize,
+    /// Characters before the first block, taken up by row labels.
+    offset: usize,
+}
+
+impl Grid<'_> {
+    fn field(&self, block: usize, row: usize, column: usize) -> &str {
+        let start = self.offset + block * BLOCK_WIDTH + column * FIELD_WIDTH;
+        let line = self.lines[row];
+        let end = (start + FIELD_WIDTH).min(line.len());
+        line.get(start.min(end)..end).unwrap_or("")
+    }
+
+    /// Field padded to the full width, as lines lose their trailing spaces.
+    fn padded_field(&self, block: usize, row: usize, column: usize) -> String {
+        format!("{:<FIELD_WIDTH$}", self.field(block, row, column))
+    }
+
+    fn error(
+        &self,
+        block: usize,
+        row: usize,
+        column: usize,
+        expected: &'static str,
+    ) -> DiagramParseError {
+        DiagramParseError::InvalidField {
+            line: self.first_line + row,
+            column: self.offset + block * BLOCK_WIDTH + column * FIELD_WIDTH + 1,
+            expected,
+            found: self.padded_field(block, row, column),
+        }
+    }
+
+    fn expect(
+        &self,
+        block: usize,
+        row: usize,
+        column: usize,
+        expected: &'static str,
+    ) -> Result<(), DiagramParseError> {
+        if self.padded_field(block, row, column) == expected {
+            Ok(())
+        } else {
+            Err(self.error(block, row, column, expected))
+        }
+    }
+
+    fn marker(&self, block: usize, row: usize, column: usize) -> Option<(Player, u8, u8)> {
+        let field = self.padded_field(block, row, column);
+        let mut chars = field.chars();
+        let owner = match chars.next()? {
+            'g' => Player::Gold,
+            's' => Player::Silver,
+            _ => return None,
+        };
+        let gatetype = chars.next()?.to_digit(4)? as u8;
+        let shift = chars.next()?.to_digit(4)? as u8;
+        Some((owner, gatetype, shift))
+    }
+}
+
+struct ParsedLayer {
+    horizontal: bool,
+    gates: [Gate; 3],
+    shifts: [u8; 3],
+}
+
+fn parse_layer(grid: &Grid<'_>, layer: usize) -> Result<ParsedLayer, DiagramParseError> {
+    const MARKER: &str = "gate marker like `g12`";
+    let horizontal = (1..4).all(|i| grid.padded_field(layer, 0, i) == "---");
+    let mut gates = [Gate {
+        allegiance: Player::Gold,
+        topleft: true,
+        gatetype: 0,
+    }; 3];
+    let mut shifts = [0; 3];
+    for (i, (gate, shift)) in (1..).zip(gates.iter_mut().zip(shifts.iter_mut())) {
+        let (topleft, bottomright) = if horizontal {
+            ((i, 0), (i, 4))
+        } else {
+            ((0, i), (4, i))
+        };
+        let (arrow_topleft, arrow_bottomright) = if horizontal {
+            (" > ", " < ")
+        } else {
+            (" v ", " ^ ")
+        };
+        let (marker, arrow, arrow_position, topleft_side) =
+            if let Some(marker) = grid.marker(layer, topleft.0, topleft.1) {
+                (marker, arrow_bottomright, bottomright, true)
+            } else {
+                let marker = grid
+                    .marker(layer, bottomright.0, bottomright.1)
+                    .ok_or_else(|| grid.error(layer, topleft.0, topleft.1, MARKER))?;
+                (marker, arrow_topleft, topleft, false)
+            };
+        grid.expect(layer, arrow_position.0, arrow_position.1, arrow)?;
+        *gate = Gate {
+            allegiance: marker.0,
+            topleft: topleft_side,
+            gatetype: marker.1,
+        };
+        *shift = marker.2;
+    }
+    let (edge_fields, edge) = if horizontal {
+        ([(4, 1), (4, 2), (4, 3)], "---")
+    } else {
+        ([(1, 0), (2, 0), (3, 0)], " | ")
+    };
+    for (row, column) in edge_fields {
+        grid.expect(layer, row, column, edge)?;
+    }
+    if !horizontal {
+        for row in 1..4 {
+            grid.expect(layer, row, 4, " | ")?;
+        }
+    }
+    Ok(ParsedLayer {
+        horizontal,
+        gates,
+        shifts,
+    })
+}
+
+/// Owner and depth of the ball in every cell.
+type CellBalls = [Option<(Player, u8)>; 9];
+
+/// Ball and depth for every cell, together with the open holes drawn in each layer.
+fn parse_cells(grid: &Grid<'_>) -> Result<(CellBalls, [[bool; 9]; 4]), DiagramParseError> {
+    let mut balls = [None; 9];
+    let mut drawn_holes = [[false; 9]; 4];
+    for (block, depth) in (0..5).zip(0_u8..) {
+        for (cell, ball) in (0_u8..).zip(balls.iter_mut()) {
+            let (row, column) = (cell as usize / 3 + 1, cell as usize % 3 + 1);
+            let (owner, hole_open) = match (depth, grid.padded_field(block, row, column).as_str()) {
+                (_, " G ") => (Some(Player::Gold), false),
+                (_, " S ") => (Some(Player::Silver), false),
+                (0..=3, " F ") => (Some(Player::Gold), true),
+                (0..=3, " f ") => (Some(Player::Silver), true),
+                (0..=3, " X ") | (4, " . ") => (None, false),
+                (0..=3, " O ") => (None, true),
+                (0..=3, _) => return Err(grid.error(block, row, column, "one of `GSFfXO`")),
+                _ => return Err(grid.error(block, row, column, "one of `GS.`")),
+            };
+            if let Some(hole) = drawn_holes.get_mut(block) {
+                hole[cell as usize] = hole_open;
+            }
+            if let Some(player) = owner {
+                if ball.is_some() {
+                    return Err(DiagramParseError::DuplicateBall(cell));
+                }
+                *ball = Some((player, depth));
+            }
+        }
+    }
+    Ok((balls, drawn_holes))
+}
+
+/// Reads a diagram as rendered by `StateView` with `RenderStyle::Ascii` or `RenderStyle::Ansi`.
+/// # Errors
+/// Will error when the diagram is malformed or does not describe a valid board and state.
+pub fn parse_state_diagram(diagram: &str) -> Result<(Board, Compact), DiagramParseError> {
+    let text = strip_colors(diagram);
+    let mut numbered_lines = (1..)
+        .zip(text.lines())
+        .filter(|&(_, line)| !line.trim().is_empty())
+        .collect::<Vec<_>>();
+    let labels = numbered_lines
+        .first()
+        .is_some_and(|&(_, line)| line.trim_start().starts_with("Layer 1"));
+    if labels {
+        numbered_lines.drain(..numbered_lines.len().min(2));
+    }
+    if numbered_lines.len() != 5 {
+        return Err(DiagramParseError::LineCount(numbered_lines.len()));
+    }
+    let grid = Grid {
+        first_line: numbered_lines[0].0,
+        lines: numbered_lines.iter().map(|&(_, line)| line).collect(),
+        offset: if labels { LABEL_WIDTH } else { 0 },
+    };
+
+    let mut layers = vec![];
+    for layer in 0..4 {
+        layers.push(parse_layer(&grid, layer)?);
+    }
+    for column in 1..4 {
+        grid.expect(4, 0, column, "---")?;
+        grid.expect(4, 4, column, "---")?;
+    }
+
+    let (balls, drawn_holes) = parse_cells(&grid)?;
+
+    let mut gates = [None; 12];
+    for (layer, gate) in layers
+        .iter()
+        .flat_map(|layer| layer.gates.iter())
+        .zip(gates.iter_mut())
+    {
+        *gate = Some(*layer);
+    }
+    let ball_cells = |owner: Player| {
+        (0_u8..)
+            .zip(balls)
+            .filter(|&(_, ball)| matches!(ball, Some((player, _)) if player == owner))
+            .map(|(cell, _)| cell)
+            .collect::<Vec<_>>()
+    };
+    let board = BoardBuilder {
+        gold_balls: ball_cells(Player::Gold),
+        silver_balls: ball_cells(Player::Silver),
+        gates_horizontal: [0, 1, 2, 3].map(|layer| Some(layers[layer].horizontal)),
+        gates,
+    }
+    .finalize_custom_inventory()
+    .map_err(DiagramParseError::InvalidBoard)?;
+
+    let ball_code = balls.iter().rev().fold(0, |code, ball| {
+        code * 5 + ball.map_or(4, |(_, depth)| u64::from(depth))
+    });
+    let shift_code = layers
+        .iter()
+        .flat_map(|layer| layer.shifts.iter())
+        .rev()
+        .fold(0, |code, shift| (code << 2) | u64::from(*shift));
+    let state = Compact::try_from_u64(
+        ball_code | (shift_code << BallBitmask::COMPRESSED_BITSIZE),
+        &board,
+    )?;
+
+    for (layer, holes) in (0_u8..).zip(drawn_holes) {
+        for (cell, hole_open) in (0_u8..).zip(holes) {
+            let actual = state.get_gate_bits() & (1 << (layer * 9 + cell)) != 0;
+            if actual != hole_open {
+                return Err(DiagramParseError::HoleMismatch { layer, cell });
+            }
+        }
+    }
+    Ok((board, state))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_state_diagram, DiagramParseError};
+    use crate::board::notation::example;
+    use crate::{Board, Compact, Player, RenderStyle, StateView};
+
+    #[test]
+    fn diagram_roundtrip() {
+        for _ in 0..20 {
+            let board = Board::random();
+            let initial_state = Compact::build_from_board(&board);
+            for (state, _) in initial_state.random_game(&board, Player::Gold) {
+                for style in [RenderStyle::Ascii, RenderStyle::Ansi] {
+                    for labels in [false, true] {
+                        let diagram = StateView::new(&board, &state)
+                            .style(style)
+                            .labels(labels)
+                            .to_string();
+                        assert_eq!(
+                            parse_state_diagram(&diagram),
+                            Ok((board.clone(), state)),
+                            "{diagram}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reject_bad_diagrams() {
+        let diagram = [
+            "   ---------        v g30 v        ---------        v g00 v        ---------",
+            "g01 X  G  O  <   |  O  X  O  |  s30 G  X  X  <   |  X  O  O  |      .  .  .",
+            " >  S  S  G s30  |  X  X  X  |  g10 X  O  X  <   |  X  X  X  |      .  .  .",
+            "s10 S  O  S  <   |  X  G  X  |   >  X  X  O s00  |  X  X  X  |      .  .  .",
+            "   ---------       g20 ^ s01       ---------       g30 ^ s20       ---------",
+        ];
+        let (board, state) = parse_state_diagram(&diagram.join("\n")).expect("Diagram is valid");
+        assert_eq!(board, example::board());
+        assert_eq!(state.get_shift(0, 0), 1);
+        assert_eq!(state.get_shift(1, 2), 1);
+
+        assert_eq!(
+            parse_state_diagram(&diagram[..4].join("\n")),
+            Err(DiagramParseError::LineCount(4))
+        );
+
+        let mut wrong_cell = diagram.map(str::to_owned);
+        wrong_cell[1].replace_range(3..6, " Q ");
+        assert_eq!(
+            parse_state_diagram(&wrong_cell.join("\n")),
+            Err(DiagramParseError::InvalidField {
+                line: 2,
+                column: 4,
+                expected: "one of `GSFfXO`",
+                found: " Q ".to_owned()
+            })
+        );
+
+        let mut duplicate = diagram.map(str::to_owned);
+        duplicate[1].replace_range(19..22, " G ");
+        assert_eq!(
+            parse_state_diagram(&duplicate.join("\n")),
+            Err(DiagramParseError::DuplicateBall(0))
+        );
+
+        let mut wrong_hole = diagram.map(str::to_owned);
+        wrong_hole[1].replace_range(25..28, " X ");
+        assert_eq!(
+            parse_state_diagram(&wrong_hole.join("\n")),
+            Err(DiagramParseError::HoleMismatch { layer: 1, cell: 2 })
+        );
+    }
+}
@@ -0,0 +1,532 @@
+//! Game records in a format modelled after PGN.
+//!
+//! A record starts with tag lines of the form `[Name "Value"]`.
+//! The `Board` tag holds the board in text notation and the `Start` tag the starting player, both are required.
+//! `Rules` holds the ruleset in its text notation and is only written for games with variations of the rules,
+//! the gate types of the board have to match its inventory.
+//! `Result` is written for convenience and has to agree with the game when present,
+//! any other tag is kept as free-form text.
+//!
+//! After the tags follow the moves with their colour, like `G2b`, optionally numbered like `1.`,
+//! and finally the result: `Gold`, `Silver`, `Both` or `*` for a game that has not ended yet.
+//!
+//! ```text
+//! [Board "GG./SSG/SGS h:gt0,sb3,st1 v:gb2,gt3,sb0 h:st3,gt1,sb0 v:gb3,gt0,sb2"]
+//! [Start "Gold"]
+//! [Event "Club night"]
+//! [Result "*"]
+//!
+//! 1. G1a S2c 2. G1a *
+//! ```
+
+use core::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use crate::{
+    Board, BoardBuilder, BoardBuildingError, BoardParseError, ColoredMove, Game, MoveError,
+    MoveParseError, Player, Ruleset, RulesetParseError, Winner,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// A game together with free-form descriptive tags, like the players, the date or the engine used.
+pub struct GameRecord {
+    tags: Vec<(String, String)>,
+    game: Game,
+}
+
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+/// Reasons a free-form tag can not be part of a record.
+pub enum TagError {
+    #[error("Tag name `{0}` may only hold ASCII letters, digits and underscores")]
+    InvalidName(String),
+    #[error("Tag `{0}` is written by the record itself")]
+    ReservedName(String),
+    #[error("Tag `{0}` appears more than once")]
+    Duplicate(String),
+}
+
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+pub enum RecordParseErrorKind {
+    #[error("Expected a tag like `[Name \"Value\"]`")]
+    InvalidTag,
+    #[error(transparent)]
+    Tag(#[from] TagError),
+    #[error("Missing tag `{0}`")]
+    MissingTag(&'static str),
+    #[error("Invalid board: {0}")]
+    InvalidBoard(#[from] BoardParseError),
+    #[error("Invalid rules: {0}")]
+    InvalidRules(#[from] RulesetParseError),
+    #[error("Board breaks the rules: {0}")]
+    BoardAgainstRules(#[from] BoardBuildingError),
+    #[error("Expected `Gold` or `Silver`, found `{0}`")]
+    InvalidPlayer(String),
+    #[error("Invalid move: {0}")]
+    InvalidMove(#[from] MoveParseError),
+    #[error("Illegal move {ply}: {error}")]
+    IllegalMove { ply: usize, error: MoveError },
+    #[error("Missing result at the end of the moves")]
+    MissingResult,
+    #[error("Recorded result {recorded} does not match the result {actual} of the moves")]
+    ResultMismatch { recorded: String, actual: String },
+}
+
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+#[error("{kind} in line {line}")]
+pub struct RecordParseError {
+    /// Line of the error, counting from 1.
+    pub line: usize,
+    pub kind: RecordParseErrorKind,
+}
+
+const fn player_name(player: Player) -> &'static str {
+    match player {
+        Player::Gold => "Gold",
+        Player::Silver => "Silver",
+    }
+}
+
+const fn result_name(winner: Winner) -> &'static str {
+    match winner {
+        Winner::None => "*",
+        Winner::Both => "Both",
+        Winner::One(player) => player_name(player),
+    }
+}
+
+fn is_result_token(token: &str) -> bool {
+    matches!(token, "*" | "Both" | "Gold" | "Silver")
+}
+
+fn is_move_number(token: &str) -> bool {
+    token.strip_suffix('.').is_some_and(|number| {
+        !number.is_empty() && number.chars().all(|digit| digit.is_ascii_digit())
+    })
+}
+
+/// Tags with a meaning of their own, they can not be used as free-form tags.
+const RESERVED_TAGS: [&str; 4] = ["Board", "Start", "Rules", "Result"];
+
+fn is_tag_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|symbol| symbol.is_ascii_alphanumeric() || symbol == '_')
+}
+
+/// Checks that a free-form tag called `name` can join `tags`.
+fn check_tag(tags: &[(String, String)], name: &str) -> Result<(), TagError> {
+    if !is_tag_name(name) {
+        Err(TagError::InvalidName(name.to_owned()))
+    } else if RESERVED_TAGS.contains(&name) {
+        Err(TagError::ReservedName(name.to_owned()))
+    } else if tags.iter().any(|tag| tag.0 == name) {
+        Err(TagError::Duplicate(name.to_owned()))
+    } else {
+        Ok(())
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Reads `[Name "Value"]`, returning name and unescaped value.
+fn parse_tag(line: &str) -> Option<(String, String)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?;
+    let (name, rest) = inner.split_once(' ')?;
+    if !is_tag_name(name) {
+        return None;
+    }
+    let quoted = rest.trim().strip_prefix('"')?.strip_suffix('"')?;
+
+    let mut value = String::new();
+    let mut chars = quoted.chars();
+    while let Some(symbol) = chars.next() {
+        match symbol {
+            '\\' => value.push(chars.next()?),
+            '"' => return None,
+            _ => value.push(symbol),
+        }
+    }
+    Some((name.to_owned(), value))
+}
+
+/// Value of a tag the record can not do without, together with its line.
+fn required_tag(
+    tag: Option<(usize, String)>,
+    name: &'static str,
+) -> Result<(usize, String), RecordParseError> {
+    tag.ok_or(RecordParseError {
+        line: 1,
+        kind: RecordParseErrorKind::MissingTag(name),
+    })
+}
+
+fn parse_player(name: String) -> Result<Player, RecordParseErrorKind> {
+    match name.as_str() {
+        "Gold" => Ok(Player::Gold),
+        "Silver" => Ok(Player::Silver),
+        _ => Err(RecordParseErrorKind::InvalidPlayer(name)),
+    }
+}
+
+/// Plays the moves given as `(line, token)` pairs, skipping move numbers.
+fn replay_moves(game: &mut Game, tokens: Vec<(usize, &str)>) -> Result<(), RecordParseError> {
+    for (line, token) in tokens
+        .into_iter()
+        .filter(|&(_, token)| !is_move_number(token))
+    {
+        let error = |kind| RecordParseError { line, kind };
+        let colored = token
+            .parse::<ColoredMove>()
+            .map_err(|cause| error(cause.into()))?;
+        let ply = game.ply() + 1;
+        colored
+            .validate(game.board())
+            .and_then(|checked| game.apply(checked))
+            .map_err(|cause| error(RecordParseErrorKind::IllegalMove { ply, error: cause }))?;
+    }
+    Ok(())
+}
+
+impl FromStr for GameRecord {
+    type Err = RecordParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tags: Vec<(String, String)> = vec![];
+        let mut board_tag = None;
+        let mut start_tag = None;
+        let mut rules_tag = None;
+        let mut recorded_result = None;
+        let mut tokens = vec![];
+
+        for (line_number, raw_line) in (1..).zip(s.lines()) {
+            let error = |kind| RecordParseError {
+                line: line_number,
+                kind,
+            };
+            let line = raw_line.trim();
+            if !line.starts_with('[') {
+                tokens.extend(line.split_whitespace().map(|token| (line_number, token)));
+                continue;
+            }
+            if !tokens.is_empty() {
+                return Err(error(RecordParseErrorKind::InvalidTag));
+            }
+
+            let (name, value) =
+                parse_tag(line).ok_or_else(|| error(RecordParseErrorKind::InvalidTag))?;
+            let slot = match name.as_str() {
+                "Board" => &mut board_tag,
+                "Start" => &mut start_tag,
+                "Rules" => &mut rules_tag,
+                "Result" => &mut recorded_result,
+                _ => {
+                    check_tag(&tags, &name).map_err(|cause| error(cause.into()))?;
+                    tags.push((name, value));
+                    continue;
+                }
+            };
+            if slot.is_some() {
+                return Err(error(TagError::Duplicate(name).into()));
+            }
+            *slot = Some((line_number, value));
+        }
+
+        let (board_line, notation) = required_tag(board_tag, "Board")?;
+        let parsed_board = notation
+            .parse::<Board>()
+            .map_err(|cause| RecordParseError {
+                line: board_line,
+                kind: cause.into(),
+            })?;
+
+        let (start_line, start_name) = required_tag(start_tag, "Start")?;
+        let starting_player = parse_player(start_name).map_err(|kind| RecordParseError {
+            line: start_line,
+            kind,
+        })?;
+
+        let last_line = s.lines().count().max(1);
+        let (result_line, result) = match tokens.pop() {
+            Some((line, token)) if is_result_token(token) => (line, token),
+            Some((line, _)) => {
+                return Err(RecordParseError {
+                    line,
+                    kind: RecordParseErrorKind::MissingResult,
+                })
+            }
+            None => {
+                return Err(RecordParseError {
+                    line: last_line,
+                    kind: RecordParseErrorKind::MissingResult,
+                })
+            }
+        };
+
+        let ruleset = match rules_tag {
+            Some((rules_line, rules)) => {
+                rules.parse::<Ruleset>().map_err(|cause| RecordParseError {
+                    line: rules_line,
+                    kind: cause.into(),
+                })?
+            }
+            None => Ruleset::default(),
+        };
+        let board = BoardBuilder::from(&parsed_board)
+            .finalize_with_ruleset(&ruleset)
+            .map_err(|cause| RecordParseError {
+                line: board_line,
+                kind: cause.into(),
+            })?;
+
+        let mut game = Game::with_ruleset(board, starting_player, ruleset);
+        replay_moves(&mut game, tokens)?;
+
+        let actual = result_name(game.winner());
+        for (line, recorded) in [Some((result_line, result.to_owned())), recorded_result]
+            .into_iter()
+            .flatten()
+        {
+            if recorded != actual {
+                return Err(RecordParseError {
+                    line,
+                    kind: RecordParseErrorKind::ResultMismatch {
+                        recorded,
+                        actual: actual.to_owned(),
+                    },
+                });
+            }
+        }
+
+        Ok(Self { tags, game })
+    }
+}
+
+impl GameRecord {
+    /// Record of a game without any tags.
+    #[must_use]
+    pub const fn new(game: Game) -> Self {
+        Self { tags: vec![], game }
+    }
+
+    /// The recorded game.
+    #[must_use]
+    pub const fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Free-form tags in the order they were added.
+    #[must_use]
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    /// Adds a free-form tag, the value may hold any text.
+    ///
+    /// # Errors
+    /// Fails for names which are not made of ASCII letters, digits and underscores,
+    /// which are already used or which the record writes itself, like `Board` or `Result`.
+    pub fn add_tag(&mut self, name: String, value: String) -> Result<(), TagError> {
+        check_tag(&self.tags, &name)?;
+        self.tags.push((name, value));
+        Ok(())
+    }
+}
+
+impl Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let result = result_name(self.game.winner());
+        writeln!(f, "[Board \"{}\"]", self.game.board())?;
+        writeln!(
+            f,
+            "[Start \"{}\"]",
+            player_name(self.game.starting_player())
+        )?;
+        if *self.game.ruleset() != Ruleset::default() {
+            writeln!(f, "[Rules \"{}\"]", self.game.ruleset())?;
+        }
+        for tag in &self.tags {
+            writeln!(f, "[{} \"{}\"]", tag.0, escape(&tag.1))?;
+        }
+        writeln!(f, "[Result \"{result}\"]")?;
+        writeln!(f)?;
+
+        let mut line = String::new();
+        for (ply, played) in self.game.moves().iter().enumerate() {
+            let mut token = played.colored(self.game.board()).to_string();
+            if ply % 2 == 0 {
+                token = format!("{}. {token}", ply / 2 + 1);
+            }
+            if !line.is_empty() && line.len() + token.len() >= 80 {
+                writeln!(f, "{line}")?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        writeln!(f, "{line}{result}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{GameRecord, RecordParseError, RecordParseErrorKind, TagError};
+    use crate::board::notation::example;
+    use crate::{
+        Board, BoardBuildingError, EndCondition, Game, MoveError, NoMovesRule, Player, Ruleset,
+        Winner,
+    };
+
+    #[test]
+    fn record_roundtrip() {
+        use rand::seq::SliceRandom as _;
+
+        for i in 0_u8..50 {
+            let mut game = Game::new(Board::random(), Player::Silver);
+            while let Some(&next_move) = game.legal_moves().choose(&mut rand::thread_rng()) {
+                game.apply(next_move).expect("Legal move was rejected");
+            }
+            // Leave some games unfinished
+            for _ in 0..(i % 3) {
+                game.undo();
+            }
+            let mut record = GameRecord::new(game);
+            for (name, value) in [("Gold", "Engine \"deep\" \\ v1"), ("Date", "2022.06.01")] {
+                record
+                    .add_tag(name.to_owned(), value.to_owned())
+                    .expect("Tag is free-form");
+            }
+            assert_eq!(
+                record.add_tag("Result".to_owned(), "Gold".to_owned()),
+                Err(TagError::ReservedName("Result".to_owned()))
+            );
+            assert_eq!(
+                record.add_tag("Date".to_owned(), "2022.06.02".to_owned()),
+                Err(TagError::Duplicate("Date".to_owned()))
+            );
+            for name in ["White Player", "Round]", "\"", ""] {
+                assert_eq!(
+                    record.add_tag(name.to_owned(), String::new()),
+                    Err(TagError::InvalidName(name.to_owned()))
+                );
+            }
+            let written = record.to_string();
+            assert_eq!(written.parse(), Ok(record), "{written}");
+        }
+    }
+
+    #[test]
+    fn house_rules_roundtrip() {
+        use rand::{rngs::StdRng, seq::SliceRandom as _, SeedableRng as _};
+
+        let ruleset = Ruleset {
+            end_condition: EndCondition::BallsOut(1),
+            no_moves: NoMovesRule::Draw,
+            gold_gate_types: [0, 1, 1, 2, 2, 3],
+            silver_gate_types: [0, 0, 1, 2, 3, 3],
+        };
+        let mut rng = StdRng::seed_from_u64(23);
+        for _ in 0..50 {
+            let board =
+                Board::random_with_ruleset(&ruleset, &mut rng).expect("Gate types are in range");
+            let mut game = Game::with_ruleset(board, Player::Gold, ruleset.clone());
+            while let Some(&next_move) = game.legal_moves().choose(&mut rng) {
+                game.apply(next_move).expect("Legal move was rejected");
+            }
+            assert_ne!(game.winner(), Winner::None);
+            let record = GameRecord::new(game);
+            let written = record.to_string();
+            assert!(written.contains(&format!("[Rules \"{ruleset}\"]")));
+            assert_eq!(written.parse(), Ok(record), "{written}");
+        }
+    }
+
+    #[test]
+    fn reject_bad_records() {
+        let header = format!("[Board \"{}\"]\n[Start \"Gold\"]\n\n", example::NOTATION);
+
+        let record = format!("{header}1. G1a S1b 2. G1a *\n")
+            .parse::<GameRecord>()
+            .expect("Record is valid");
+        assert_eq!(record.game().ply(), 3);
+
+        assert_eq!(
+            format!("{header}1. G1a\nS1b 2. G1a G1a *\n").parse::<GameRecord>(),
+            Err(RecordParseError {
+                line: 5,
+                kind: RecordParseErrorKind::IllegalMove {
+                    ply: 4,
+                    error: MoveError::WrongOwner {
+                        layer: 0,
+                        gate: 0,
+                        player: Player::Silver
+                    }
+                }
+            })
+        );
+        assert_eq!(
+            format!("{header}1. G1a S1a *\n").parse::<GameRecord>(),
+            Err(RecordParseError {
+                line: 4,
+                kind: RecordParseErrorKind::IllegalMove {
+                    ply: 2,
+                    error: MoveError::WrongOwner {
+                        layer: 0,
+                        gate: 0,
+                        player: Player::Silver
+                    }
+                }
+            })
+        );
+        assert_eq!(
+            format!("{header}1. G1a Gold\n").parse::<GameRecord>(),
+            Err(RecordParseError {
+                line: 4,
+                kind: RecordParseErrorKind::ResultMismatch {
+                    recorded: "Gold".to_owned(),
+                    actual: "*".to_owned()
+                }
+            })
+        );
+        assert_eq!(
+            format!("{header}1. G1a\n").parse::<GameRecord>(),
+            Err(RecordParseError {
+                line: 4,
+                kind: RecordParseErrorKind::MissingResult
+            })
+        );
+        assert_eq!(
+            format!("{header}[Board \"{}\"]\n*", example::NOTATION).parse::<GameRecord>(),
+            Err(RecordParseError {
+                line: 4,
+                kind: RecordParseErrorKind::Tag(TagError::Duplicate("Board".to_owned()))
+            })
+        );
+        assert_eq!(
+            format!("[Rules \"all pass 011223 001233\"]\n{header}*").parse::<GameRecord>(),
+            Err(RecordParseError {
+                line: 2,
+                kind: RecordParseErrorKind::BoardAgainstRules(
+                    BoardBuildingError::GateInventoryIncorrect(Player::Gold)
+                )
+            })
+        );
+        assert_eq!(
+            "[Start \"Gold\"]\n*".parse::<GameRecord>(),
+            Err(RecordParseError {
+                line: 1,
+                kind: RecordParseErrorKind::MissingTag("Board")
+            })
+        );
+    }
+}
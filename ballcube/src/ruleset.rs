@@ -0,0 +1,314 @@
+use core::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use crate::{Board, BoardBuilder, BoardBuildingError, Player, STANDARD_GATE_TYPES};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// When a game is over. Finished games are won by the player with more balls out of the cube.
+pub enum EndCondition {
+    /// Game ends once all balls of a player are out, as in the original game.
+    #[default]
+    AllBallsOut,
+    /// Game ends once a player has this many balls out.
+    BallsOut(u8),
+    /// Game ends after this many shifts in total, or earlier once all balls of a player are out.
+    ShiftLimit(u8),
+}
+
+impl Display for EndCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::AllBallsOut => write!(f, "all"),
+            Self::BallsOut(count) => write!(f, "balls:{count}"),
+            Self::ShiftLimit(count) => write!(f, "shifts:{count}"),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+#[error("Expected `all`, `balls:<count>` or `shifts:<count>` as end condition, found `{0}`")]
+pub struct EndConditionParseError(pub String);
+
+impl FromStr for EndCondition {
+    type Err = EndConditionParseError;
+
+    /// Reads the notation of `Display`, like `balls:2` for the first player with two balls out.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || EndConditionParseError(s.to_owned());
+        match s.split_once(':') {
+            None if s == "all" => Ok(Self::AllBallsOut),
+            Some(("balls", count)) => Ok(Self::BallsOut(
+                count.parse().map_err(|_parse_error| error())?,
+            )),
+            Some(("shifts", count)) => Ok(Self::ShiftLimit(
+                count.parse().map_err(|_parse_error| error())?,
+            )),
+            _ => Err(error()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// What happens when the player on the move has pulled out all of their gates before the game is over.
+pub enum NoMovesRule {
+    /// The other player keeps moving alone.
+    #[default]
+    Pass,
+    /// The player who cannot move loses.
+    Loss,
+    /// The game ends in a draw.
+    Draw,
+}
+
+impl Display for NoMovesRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Pass => write!(f, "pass"),
+            Self::Loss => write!(f, "loss"),
+            Self::Draw => write!(f, "draw"),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+#[error("Expected `pass`, `loss` or `draw` for a player without moves, found `{0}`")]
+pub struct NoMovesRuleParseError(pub String);
+
+impl FromStr for NoMovesRule {
+    type Err = NoMovesRuleParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pass" => Ok(Self::Pass),
+            "loss" => Ok(Self::Loss),
+            "draw" => Ok(Self::Draw),
+            _ => Err(NoMovesRuleParseError(s.to_owned())),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// Variations of the rules a game is played with, the default being the original game.
+///
+/// Gate types are checked while parsing or deserializing, rulesets built otherwise can be checked with `check_gate_types`.
+pub struct Ruleset {
+    pub end_condition: EndCondition,
+    pub no_moves: NoMovesRule,
+    /// Types of the six gates of gold in any order, 3 meaning no hole.
+    pub gold_gate_types: [u8; 6],
+    /// Types of the six gates of silver in any order, 3 meaning no hole.
+    pub silver_gate_types: [u8; 6],
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Self {
+            end_condition: EndCondition::default(),
+            no_moves: NoMovesRule::default(),
+            gold_gate_types: STANDARD_GATE_TYPES,
+            silver_gate_types: STANDARD_GATE_TYPES,
+        }
+    }
+}
+
+impl Display for Ruleset {
+    /// Writes end condition, rule for players without moves and the gate types of both players,
+    /// like `balls:2 loss 001233 001233`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let types = |types: [u8; 6]| types.map(|gate_type| gate_type.to_string()).concat();
+        write!(
+            f,
+            "{} {} {} {}",
+            self.end_condition,
+            self.no_moves,
+            types(self.gold_gate_types),
+            types(self.silver_gate_types)
+        )
+    }
+}
+
+#[derive(thiserror::Error, Clone, Copy, Debug, PartialEq, Eq)]
+#[error("Gate type {gate_type} of {player:?} is larger than 3")]
+pub struct GateTypeError {
+    pub player: Player,
+    pub gate_type: u8,
+}
+
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+pub enum RulesetParseError {
+    #[error("Expected end condition, rule for players without moves and gate types of both players, found `{0}`")]
+    WrongFieldCount(String),
+    #[error(transparent)]
+    EndCondition(#[from] EndConditionParseError),
+    #[error(transparent)]
+    NoMoves(#[from] NoMovesRuleParseError),
+    #[error("Expected six gate types from 0 to 3 like `001233`, found `{0}`")]
+    GateTypes(String),
+}
+
+impl FromStr for Ruleset {
+    type Err = RulesetParseError;
+
+    /// Reads the notation of `Display`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_whitespace().collect::<Vec<_>>()[..] {
+            [end_condition, no_moves, gold, silver] => Ok(Self {
+                end_condition: end_condition.parse()?,
+                no_moves: no_moves.parse()?,
+                gold_gate_types: Self::parse_gate_types(gold)?,
+                silver_gate_types: Self::parse_gate_types(silver)?,
+            }),
+            _ => Err(RulesetParseError::WrongFieldCount(s.to_owned())),
+        }
+    }
+}
+
+impl Ruleset {
+    #[must_use]
+    pub const fn gate_types(&self, player: Player) -> [u8; 6] {
+        match player {
+            Player::Gold => self.gold_gate_types,
+            Player::Silver => self.silver_gate_types,
+        }
+    }
+
+    /// Reads the six gate types of one player like `001233`, as written in the notation of `Display`.
+    /// # Errors
+    /// Will error unless given exactly six digits from 0 to 3.
+    pub fn parse_gate_types(notation: &str) -> Result<[u8; 6], RulesetParseError> {
+        notation
+            .chars()
+            .map(|symbol| u8::try_from(symbol.to_digit(4)?).ok())
+            .collect::<Option<Vec<_>>>()
+            .and_then(|types| types.try_into().ok())
+            .ok_or_else(|| RulesetParseError::GateTypes(notation.to_owned()))
+    }
+
+    /// Checks that all gate types are from 0 to 3.
+    /// # Errors
+    /// Will error with the first gate type larger than 3.
+    pub fn check_gate_types(&self) -> Result<(), GateTypeError> {
+        for player in [Player::Gold, Player::Silver] {
+            if let Some(&gate_type) = self
+                .gate_types(player)
+                .iter()
+                .find(|candidate| **candidate > 3)
+            {
+                return Err(GateTypeError { player, gate_type });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the gates of `board` have the gate types of the ruleset.
+    /// # Errors
+    /// Will error with `GateInventoryIncorrect` when the gate types of a player differ.
+    pub fn check_board(&self, board: &Board) -> Result<(), BoardBuildingError> {
+        BoardBuilder::from(board)
+            .finalize_with_ruleset(self)
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{EndCondition, GateTypeError, NoMovesRule, Ruleset};
+    use crate::{Board, BoardBuildingError, Player};
+
+    #[test]
+    fn end_condition_notation() {
+        for condition in [
+            EndCondition::AllBallsOut,
+            EndCondition::BallsOut(2),
+            EndCondition::ShiftLimit(20),
+        ] {
+            assert_eq!(condition.to_string().parse(), Ok(condition));
+        }
+        assert_eq!("balls:2".parse(), Ok(EndCondition::BallsOut(2)));
+        for invalid in ["", "all:1", "balls", "balls:x", "shifts:-1", "moves:3"] {
+            invalid
+                .parse::<EndCondition>()
+                .expect_err("End condition notation is invalid");
+        }
+        for rule in [NoMovesRule::Pass, NoMovesRule::Loss, NoMovesRule::Draw] {
+            assert_eq!(rule.to_string().parse(), Ok(rule));
+        }
+        "Pass"
+            .parse::<NoMovesRule>()
+            .expect_err("Rules are written in lower case");
+
+        let ruleset = Ruleset {
+            end_condition: EndCondition::BallsOut(2),
+            no_moves: NoMovesRule::Loss,
+            gold_gate_types: [0, 1, 1, 2, 2, 3],
+            silver_gate_types: [3, 0, 0, 1, 2, 3],
+        };
+        assert_eq!(ruleset.to_string(), "balls:2 loss 011223 300123");
+        assert_eq!(ruleset.to_string().parse(), Ok(ruleset));
+        assert_eq!(
+            Ruleset::default().to_string().parse(),
+            Ok(Ruleset::default())
+        );
+        for invalid in [
+            "all pass 001233",
+            "all pass 001233 001234",
+            "all pass 00123 001233",
+        ] {
+            invalid
+                .parse::<Ruleset>()
+                .expect_err("Ruleset notation is invalid");
+        }
+    }
+
+    #[test]
+    fn gate_inventory() {
+        use rand::{rngs::StdRng, SeedableRng as _};
+
+        let ruleset = Ruleset {
+            gold_gate_types: [0, 1, 1, 2, 2, 3],
+            silver_gate_types: [0, 0, 0, 3, 3, 3],
+            ..Ruleset::default()
+        };
+        let mut rng = StdRng::seed_from_u64(25);
+        for _ in 0..100 {
+            let board =
+                Board::random_with_ruleset(&ruleset, &mut rng).expect("Gate types are in range");
+            assert_eq!(ruleset.check_board(&board), Ok(()));
+            for player in [Player::Gold, Player::Silver] {
+                let mut types = (0..12)
+                    .map(|gate| board.layer(gate / 3).gate(gate % 3))
+                    .filter(|gate| gate.owner() == player)
+                    .map(|gate| gate.gatetype())
+                    .collect::<Vec<_>>();
+                types.sort_unstable();
+                assert_eq!(types, ruleset.gate_types(player));
+            }
+            assert_eq!(
+                Ruleset::default().check_board(&board),
+                Err(BoardBuildingError::GateInventoryIncorrect(Player::Gold))
+            );
+            assert_eq!(board.to_string().parse(), Ok(board));
+        }
+
+        let invalid = Ruleset {
+            silver_gate_types: [0, 0, 1, 2, 3, 7],
+            ..Ruleset::default()
+        };
+        assert_eq!(
+            invalid.check_gate_types(),
+            Err(GateTypeError {
+                player: Player::Silver,
+                gate_type: 7
+            })
+        );
+        assert!(matches!(
+            Board::random_with_ruleset(&invalid, &mut rng),
+            Err(BoardBuildingError::GateTypeOutOfRange(_))
+        ));
+    }
+}
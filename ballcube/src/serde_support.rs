@@ -0,0 +1,252 @@
+//! Serde support, enabled with the `serde` feature.
+//!
+//! Boards and moves are stored in their text notation, games as board, starting player and moves.
+//! Everything is validated while deserializing, games are replayed move by move.
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    Board, ColoredMove, Compact, EndCondition, Game, GameRecord, Move, NoMovesRule, Player, Ruleset,
+};
+
+/// Implements serde for types with a text notation through their `Display` and `FromStr` impls.
+macro_rules! serde_via_notation {
+    ($($t:ty),*) => {
+        $(
+            impl Serialize for $t {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.collect_str(self)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $t {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    String::deserialize(deserializer)?
+                        .parse()
+                        .map_err(D::Error::custom)
+                }
+            }
+        )*
+    };
+}
+
+serde_via_notation!(Board, Move, ColoredMove);
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Compact")]
+struct RawCompact {
+    balls: u64,
+    gates: u64,
+    gate_shifts: u64,
+}
+
+impl Serialize for Compact {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        RawCompact {
+            balls: self.get_ball_bits(),
+            gates: self.get_gate_bits(),
+            gate_shifts: self.get_shift_bits(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Compact {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = RawCompact::deserialize(deserializer)?;
+        Self::from_raw_parts(raw.balls, raw.gates, raw.gate_shifts).ok_or_else(|| {
+            D::Error::custom("Balls, gates or gate shifts are out of range or overlap")
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Ruleset", default)]
+/// Fields missing in rulesets stored by older versions keep their default.
+struct RawRuleset {
+    end_condition: EndCondition,
+    no_moves: NoMovesRule,
+    gold_gate_types: [u8; 6],
+    silver_gate_types: [u8; 6],
+}
+
+impl Default for RawRuleset {
+    fn default() -> Self {
+        let ruleset = Ruleset::default();
+        Self {
+            end_condition: ruleset.end_condition,
+            no_moves: ruleset.no_moves,
+            gold_gate_types: ruleset.gold_gate_types,
+            silver_gate_types: ruleset.silver_gate_types,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Ruleset {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = RawRuleset::deserialize(deserializer)?;
+        let ruleset = Self {
+            end_condition: raw.end_condition,
+            no_moves: raw.no_moves,
+            gold_gate_types: raw.gold_gate_types,
+            silver_gate_types: raw.silver_gate_types,
+        };
+        ruleset.check_gate_types().map_err(D::Error::custom)?;
+        Ok(ruleset)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Game")]
+struct RawGame {
+    board: Board,
+    starting_player: Player,
+    /// Missing in games stored before rulesets existed.
+    #[serde(default)]
+    ruleset: Ruleset,
+    moves: Vec<Move>,
+}
+
+impl Serialize for Game {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        RawGame {
+            board: self.board().clone(),
+            starting_player: self.starting_player(),
+            ruleset: self.ruleset().clone(),
+            moves: self.moves().to_vec(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Game {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = RawGame::deserialize(deserializer)?;
+        Self::from_moves_with_ruleset(raw.board, raw.starting_player, raw.ruleset, raw.moves)
+            .map_err(D::Error::custom)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "GameRecord")]
+struct RawGameRecord {
+    tags: Vec<(String, String)>,
+    game: Game,
+}
+
+impl<'de> Deserialize<'de> for GameRecord {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = RawGameRecord::deserialize(deserializer)?;
+        let mut record = Self::new(raw.game);
+        for (name, value) in raw.tags {
+            record.add_tag(name, value).map_err(D::Error::custom)?;
+        }
+        Ok(record)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::board::notation::example;
+    use crate::{
+        Board, Compact, EndCondition, Game, GameRecord, Move, NoMovesRule, Player, Ruleset, Winner,
+    };
+
+    #[test]
+    fn json_roundtrip() {
+        for _ in 0..20 {
+            let board = Board::random();
+            let states = Compact::build_from_board(&board).random_game(&board, Player::Gold);
+            let game = Game::from_moves(board.clone(), Player::Gold, states.iter().map(|x| x.1))
+                .expect("Random game is legal");
+
+            let board_json = serde_json::to_string(&board).expect("Could not serialize board");
+            assert_eq!(board_json, format!("\"{board}\""));
+            assert_eq!(serde_json::from_str::<Board>(&board_json).ok(), Some(board));
+
+            for &(state, played) in &states {
+                let state_json = serde_json::to_string(&state).expect("Could not serialize state");
+                assert_eq!(
+                    serde_json::from_str::<Compact>(&state_json).ok(),
+                    Some(state)
+                );
+                let move_json = serde_json::to_string(&played).expect("Could not serialize move");
+                assert_eq!(serde_json::from_str::<Move>(&move_json).ok(), Some(played));
+            }
+
+            let winner_json =
+                serde_json::to_string(&game.winner()).expect("Could not serialize winner");
+            assert_eq!(
+                serde_json::from_str::<Winner>(&winner_json).ok(),
+                Some(game.winner())
+            );
+
+            let mut record = GameRecord::new(game);
+            record
+                .add_tag("Event".to_owned(), "Test".to_owned())
+                .expect("Tag is free-form");
+            let record_json = serde_json::to_string(&record).expect("Could not serialize record");
+            assert_eq!(
+                serde_json::from_str::<GameRecord>(&record_json).ok(),
+                Some(record)
+            );
+        }
+    }
+
+    #[test]
+    fn reject_invalid_json() {
+        let board = example::NOTATION;
+        serde_json::from_str::<Board>(&format!("\"{board}\"")).expect("Board notation is valid");
+        serde_json::from_str::<Board>("\"GG./SSG/SGS\"").expect_err("Gates are missing");
+        serde_json::from_str::<Move>("\"5a\"").expect_err("There is no layer 5");
+        serde_json::from_str::<Compact>(
+            r#"{"balls": 3, "gates": 0, "gate_shifts": 1099511627776}"#,
+        )
+        .expect_err("Gate shifts are out of range");
+        serde_json::from_str::<Compact>(r#"{"balls": 513, "gates": 0, "gate_shifts": 0}"#)
+            .expect_err("Balls are out of range");
+
+        let legal =
+            format!(r#"{{"board": "{board}", "starting_player": "Gold", "moves": ["1a", "1b"]}}"#);
+        serde_json::from_str::<Game>(&legal).expect("Moves are legal");
+        let illegal =
+            format!(r#"{{"board": "{board}", "starting_player": "Gold", "moves": ["1a", "1a"]}}"#);
+        serde_json::from_str::<Game>(&illegal).expect_err("Gate 1a can not be shifted twice");
+        let record = format!(r#"{{"tags": [["Result", "Gold"]], "game": {legal}}}"#);
+        assert!(serde_json::from_str::<GameRecord>(&record)
+            .expect_err("Result is not a free-form tag")
+            .to_string()
+            .contains("Tag `Result` is written by the record itself"));
+
+        let ruleset = Ruleset {
+            end_condition: EndCondition::BallsOut(2),
+            no_moves: NoMovesRule::Loss,
+            ..Ruleset::default()
+        };
+        let game = Game::with_ruleset(
+            board.parse().expect("Board notation is valid"),
+            Player::Gold,
+            ruleset.clone(),
+        );
+        let json = serde_json::to_string(&game).expect("Could not serialize game");
+        assert_eq!(
+            serde_json::from_str::<Game>(&json)
+                .map(|parsed| parsed.ruleset().clone())
+                .ok(),
+            Some(ruleset)
+        );
+
+        let broken = json.replace("[0,0,1,2,3,3]", "[0,0,1,2,3,4]");
+        assert!(serde_json::from_str::<Game>(&broken)
+            .expect_err("Gate type 4 does not exist")
+            .to_string()
+            .contains("Gate type 4 of Gold is larger than 3"));
+        assert_eq!(
+            serde_json::from_str::<Ruleset>(r#"{"no_moves": "Draw"}"#).ok(),
+            Some(Ruleset {
+                no_moves: NoMovesRule::Draw,
+                ..Ruleset::default()
+            })
+        );
+    }
+}
@@ -12,6 +12,15 @@ impl BallBitmask {
         Self { mask }
     }
 
+    /// Whether the mask describes 8 balls in distinct cells, each resting on some layer or fallen out.
+    pub fn is_valid(self) -> bool {
+        // One bit for each of the 5 depths of the first cell
+        let cell_mask = 0x0010_0804_0201_u64;
+        self.mask < (1 << 45)
+            && self.mask.count_ones() == 8
+            && (0..9).all(|cell| (self.mask & (cell_mask << cell)).count_ones() <= 1)
+    }
+
     pub fn depth(self) -> [u8; 9] {
         let mut ballmask = 1_u64;
         for _ in 0..4 {
@@ -45,14 +54,17 @@ impl BallBitmask {
     pub fn decompress(mut compressed_balls: u64, board: &Board) -> Self {
         let mut depths = [0; 9];
         for current_depth in &mut depths {
-            *current_depth = compressed_balls % 5;
+            *current_depth = (compressed_balls % 5) as u8;
             compressed_balls /= 5;
         }
+        Self::from_depths(depths, board)
+    }
 
+    /// Depths of empty cells are ignored.
+    pub fn from_depths(depths: [u8; 9], board: &Board) -> Self {
         let mut result = 0;
-        for (index, depth) in (0..).zip(depths) {
-            if board.ball(index as u8).is_none() {
-                debug_assert_eq!(depth, 4);
+        for (index, depth) in (0_u8..).zip(depths) {
+            if board.ball(index).is_none() {
                 continue;
             }
             result |= 1_u64 << (depth * 9 + index);
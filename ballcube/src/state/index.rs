@@ -0,0 +1,209 @@
+use crate::{Board, Compact, Player};
+
+use super::{
+    two_bit_array_add,
+    validation::{cell_shifts, possible_depths},
+};
+
+/// Number of gate shift combinations, 2 bits for each of the 12 gates.
+const SHIFT_VECTORS: usize = 1 << 24;
+/// Shift vectors between two stored prefix sums.
+const OFFSET_STRIDE: usize = 64;
+
+/// Ranks the states of a single board into a dense range, for flat tablebases.
+///
+/// States are ordered by their gate shifts and then by the depth of each ball.
+/// Every shift vector in which the players took turns gets room for all combinations of depths
+/// that the balls could rest at, so some indices belong to states that cannot be reached by playing.
+pub struct StateIndexer {
+    board: Board,
+    /// Cells holding a ball.
+    ball_cells: [usize; 8],
+    /// Depth bitmask for every cell and every combination of the shifts of the four gates above it.
+    cell_depths: [[u8; 256]; 9],
+    /// Bit position of the shift of the gate covering a cell, for each cell and layer.
+    cell_gates: [[u8; 4]; 9],
+    gold_shift_mask: u64,
+    /// Number of states before every `OFFSET_STRIDE`th shift vector.
+    offsets: Vec<u64>,
+    count: u64,
+}
+
+impl StateIndexer {
+    /// # Panics
+    /// Never.
+    #[must_use]
+    pub fn new(board: &Board) -> Self {
+        let mut cell_gates = [[0; 4]; 9];
+        let mut cell_depths = [[0; 256]; 9];
+        let mut gold_shift_mask = 0;
+        for layer in 0..4 {
+            for gate in 0..3 {
+                if board.layer(layer).gate(gate).owner() == Player::Gold {
+                    gold_shift_mask |= 0b11 << ((layer * 3 + gate) * 2);
+                }
+            }
+        }
+        for (cell, (gates, depths)) in (0..).zip(cell_gates.iter_mut().zip(cell_depths.iter_mut()))
+        {
+            if board.ball(cell).is_none() {
+                continue;
+            }
+            let mut positions = [[0; 3]; 4];
+            for (layer, layer_positions) in (0..).zip(positions.iter_mut()) {
+                *layer_positions = [0, 1, 2].map(|gate| (layer * 3 + gate) * 2);
+            }
+            *gates = cell_shifts(board, &positions, cell);
+            for (key, depth) in (0_u8..=255).zip(depths.iter_mut()) {
+                let shifts = [0, 1, 2, 3].map(|layer| (key >> (layer * 2)) & 0b11);
+                *depth = possible_depths(board, cell, shifts);
+            }
+        }
+
+        let mut ball_cells = [0; 8];
+        for (ball, cell) in ball_cells
+            .iter_mut()
+            .zip((0..9).filter(|cell| board.ball(*cell as u8).is_some()))
+        {
+            *ball = cell;
+        }
+
+        let mut result = Self {
+            board: board.clone(),
+            ball_cells,
+            cell_depths,
+            cell_gates,
+            gold_shift_mask,
+            offsets: Vec::with_capacity(SHIFT_VECTORS / OFFSET_STRIDE),
+            count: 0,
+        };
+        for block in (0..SHIFT_VECTORS as u64).step_by(OFFSET_STRIDE) {
+            result.offsets.push(result.count);
+            for shifts in block..block + OFFSET_STRIDE as u64 {
+                result.count += result.states_with_shifts(shifts);
+            }
+        }
+        result
+    }
+
+    /// Number of indices, every index of a state on the board is smaller.
+    #[must_use]
+    pub const fn count(&self) -> u64 {
+        self.count
+    }
+
+    fn depth_mask(&self, cell: usize, shifts: u64) -> u8 {
+        let key = self.cell_gates[cell].iter().rev().fold(0, |key, position| {
+            (key << 2) | ((shifts >> position) & 0b11)
+        });
+        self.cell_depths[cell][key as usize]
+    }
+
+    /// Number of states with the given value of `Compact::get_shift_bits`.
+    fn states_with_shifts(&self, shifts: u64) -> u64 {
+        let gold = two_bit_array_add(shifts & self.gold_shift_mask);
+        let silver = two_bit_array_add(shifts & !self.gold_shift_mask);
+        if gold.abs_diff(silver) > 1 {
+            return 0;
+        }
+        self.ball_cells
+            .iter()
+            .map(|cell| u64::from(self.depth_mask(*cell, shifts).count_ones()))
+            .product()
+    }
+
+    /// Position of `state` in `0..count()`.
+    /// Returns `None` when the state breaks the rules of the game on the board of this indexer.
+    #[must_use]
+    pub fn index(&self, state: &Compact) -> Option<u64> {
+        let shifts = state.get_shift_bits();
+        let states = self.states_with_shifts(shifts);
+        if states == 0 {
+            return None;
+        }
+        let block = shifts as usize / OFFSET_STRIDE;
+        let mut result = self.offsets[block];
+        for previous in (block * OFFSET_STRIDE) as u64..shifts {
+            result += self.states_with_shifts(previous);
+        }
+
+        let depths = state.depth();
+        let mut depth_index = 0;
+        for &cell in self.ball_cells.iter().rev() {
+            let depth = depths[cell];
+            let mask = self.depth_mask(cell, shifts);
+            if mask & (1 << depth) == 0 {
+                return None;
+            }
+            let below = mask & ((1 << depth) - 1);
+            depth_index =
+                depth_index * u64::from(mask.count_ones()) + u64::from(below.count_ones());
+        }
+        Some(result + depth_index)
+    }
+
+    /// The state at position `index`, inverse of `StateIndexer::index`.
+    /// Returns `None` for indices of `count()` or larger.
+    /// # Panics
+    /// Never.
+    #[must_use]
+    pub fn state(&self, index: u64) -> Option<Compact> {
+        if index >= self.count {
+            return None;
+        }
+        let block = self.offsets.partition_point(|offset| *offset <= index) - 1;
+        let mut depth_index = index - self.offsets[block];
+        let mut shifts = (block * OFFSET_STRIDE) as u64;
+        loop {
+            let states = self.states_with_shifts(shifts);
+            if depth_index < states {
+                break;
+            }
+            depth_index -= states;
+            shifts += 1;
+        }
+
+        let mut depths = [4; 9];
+        for &cell in &self.ball_cells {
+            let mask = self.depth_mask(cell, shifts);
+            let options = u64::from(mask.count_ones());
+            let choice = depth_index % options;
+            depth_index /= options;
+            depths[cell] = (0..5)
+                .filter(|depth| mask & (1 << depth) != 0)
+                .nth(choice as usize)?;
+        }
+        let gate_shifts = [0, 1, 2, 3]
+            .map(|layer| [0, 1, 2].map(|gate| ((shifts >> ((layer * 3 + gate) * 2)) & 0b11) as u8));
+        Some(Compact::from_parts(&self.board, depths, gate_shifts))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::StateIndexer;
+    use crate::{Board, Compact, Player};
+
+    #[test]
+    fn state_index_roundtrip() {
+        let board = Board::random();
+        let indexer = StateIndexer::new(&board);
+        assert_eq!(indexer.state(indexer.count()), None);
+
+        let initial_state = Compact::build_from_board(&board);
+        for _ in 0..20 {
+            for (state, _) in initial_state.random_game(&board, Player::Gold) {
+                let index = indexer.index(&state).expect("Played states have an index");
+                assert!(index < indexer.count());
+                assert_eq!(indexer.state(index), Some(state));
+            }
+        }
+
+        let step = indexer.count() / 1000 + 1;
+        for index in (0..indexer.count()).step_by(step as usize) {
+            let state = indexer.state(index).expect("Index is in range");
+            assert_eq!(indexer.index(&state), Some(index));
+            assert_eq!(Compact::try_from_u64(u64::from(&state), &board), Ok(state));
+        }
+    }
+}
@@ -1,8 +1,45 @@
-use rand::seq::IteratorRandom;
+use rand::{seq::IteratorRandom as _, Rng};
 
-use crate::{Board, Move, MoveChecker, Player, Winner, WinningChecker};
+use crate::{Board, Move, MoveChecker, Player, Ruleset, Winner, WinningChecker};
 mod ball_bitmask;
-use ball_bitmask::BallBitmask;
+mod index;
+mod preview;
+mod validation;
+mod zobrist;
+pub use ball_bitmask::BallBitmask;
+pub use index::StateIndexer;
+pub use preview::{BallDrop, MoveEffect};
+pub use validation::StateDecodingError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Everything needed to take back a single gate shift with [`Compact::unshift_gate`].
+pub struct ShiftRecord {
+    layer: u8,
+    gate: u8,
+    balls: BallBitmask,
+}
+
+impl ShiftRecord {
+    #[must_use]
+    pub const fn layer(self) -> u8 {
+        self.layer
+    }
+
+    #[must_use]
+    pub const fn gate(self) -> u8 {
+        self.gate
+    }
+}
+
+#[derive(thiserror::Error, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TurnError {
+    #[error("Gold shifted {gold} times and silver {silver} times, which is impossible when {starting_player:?} starts")]
+    ImpossibleShiftCount {
+        gold: u8,
+        silver: u8,
+        starting_player: Player,
+    },
+}
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 /// Compact representation of the position of the balls and the gates.
@@ -11,6 +48,8 @@ pub struct Compact {
     balls: BallBitmask,
     gates: u64,
     gate_shifts: u64,
+    /// Zobrist key of `balls` and `gate_shifts`.
+    hash: u64,
 }
 
 impl From<&Compact> for u64 {
@@ -37,6 +76,11 @@ const fn mirror_gates(gates: u64) -> u64 {
         | ((gates & 0x0000_0009_2492_4924) >> 2)
 }
 
+/// Hole layout of a gate with the given type after `shift` pulls, as seen from the topleft side.
+const fn gate_bits(gatetype: u8, shift: u8) -> u64 {
+    (((1 << gatetype) & 0b111) >> shift) | ((0b111 << (3 - shift)) & 0b111)
+}
+
 fn two_bit_array_add(tba: u64) -> u8 {
     let it_one = (tba & 0x0033_3333) + ((tba & 0x00CC_CCCC) >> 2);
     let it_two = (it_one & 0x000F_0F0F) + ((it_one & 0x00F0_F0F0) >> 4);
@@ -47,15 +91,17 @@ fn two_bit_array_add(tba: u64) -> u8 {
 }
 
 impl Compact {
+    /// Decodes a state without checking that it can occur, see `try_from_u64` for codes from users.
+    /// Bits that are not part of a state code are ignored, so every code gives some state.
     #[must_use]
     pub fn from_u64(mut int: u64, board: &Board) -> Self {
         let mut result = Self::build_from_board(board);
 
         let ball_bits = int & ((1_u64 << BallBitmask::COMPRESSED_BITSIZE) - 1);
         int >>= BallBitmask::COMPRESSED_BITSIZE;
-        result.balls = BallBitmask::decompress(ball_bits, board);
+        result.set_balls(BallBitmask::decompress(ball_bits, board));
 
-        let gate_shifts = int;
+        let gate_shifts = int & 0x00FF_FFFF;
         for layer in 0..4 {
             for gate in 0..3 {
                 for _ in 0..(int & 0b11) {
@@ -68,25 +114,80 @@ impl Compact {
         result
     }
 
+    /// Builds the state in which every ball rests at the given depth and every gate has been shifted the given number of times.
+    pub(crate) fn from_parts(board: &Board, depths: [u8; 9], shifts: [[u8; 3]; 4]) -> Self {
+        let mut result = Self::build_from_board(board);
+        for (layer, layer_shifts) in (0..).zip(shifts) {
+            for (gate, shift) in (0..).zip(layer_shifts) {
+                for _ in 0..shift {
+                    result.shift_gate_raw(board, layer, gate);
+                }
+            }
+        }
+        result.set_balls(BallBitmask::from_depths(depths, board));
+        result
+    }
+
+    /// Reassembles a state from the values of `get_ball_bits`, `get_gate_bits` and `get_shift_bits`.
+    /// Returns `None` when the values are out of range or balls float above open holes.
     #[must_use]
-    pub fn random_game(mut self, board: &Board, starting_player: Player) -> Vec<(Self, Move)> {
+    pub fn from_raw_parts(balls: u64, gates: u64, gate_shifts: u64) -> Option<Self> {
+        let bitmask = BallBitmask::new(balls);
+        if !bitmask.is_valid() || gates >= (1 << 36) || gate_shifts >= (1 << 24) {
+            return None;
+        }
+        if bitmask.get_mask() & gates != 0 {
+            return None;
+        }
+        Some(Self {
+            balls: bitmask,
+            gates,
+            gate_shifts,
+            hash: zobrist::state_key(bitmask, gate_shifts),
+        })
+    }
+
+    /// Plays random moves under the original rules until the game is over, a player without moves passes.
+    #[must_use]
+    pub fn random_game(self, board: &Board, starting_player: Player) -> Vec<(Self, Move)> {
+        self.random_game_with_rng(board, starting_player, &mut rand::thread_rng())
+    }
+
+    /// Like `random_game`, but picks the moves with `rng`, so a seeded generator always plays the same game.
+    #[must_use]
+    pub fn random_game_with_rng<R: Rng + ?Sized>(
+        self,
+        board: &Board,
+        starting_player: Player,
+        rng: &mut R,
+    ) -> Vec<(Self, Move)> {
+        self.random_game_with_ruleset(board, starting_player, &Ruleset::default(), rng)
+    }
+
+    /// Like `random_game_with_rng`, but plays until the game is over under `ruleset`.
+    #[must_use]
+    pub fn random_game_with_ruleset<R: Rng + ?Sized>(
+        mut self,
+        board: &Board,
+        starting_player: Player,
+        ruleset: &Ruleset,
+        rng: &mut R,
+    ) -> Vec<(Self, Move)> {
         let move_generator = MoveChecker::new(board);
-        let win_checker = WinningChecker::new(board);
+        let win_checker = WinningChecker::with_ruleset(board, ruleset);
         let mut result = vec![];
-        while win_checker.won(&self) == Winner::None {
-            let player = if result.len() % 2 == 0 {
-                starting_player
-            } else {
-                starting_player.other()
-            };
-            let m = *move_generator
-                .moves(&self, player)
-                .iter()
-                .choose(&mut rand::thread_rng())
-                .expect("No moves left, but no one won yet");
-
-            self.shift_gate(board, m.layer(), m.gate());
-            result.push((self, m));
+        let mut player = starting_player;
+        while win_checker.outcome(&self, player) == Winner::None {
+            // Without moves the outcome has not ended the game, so the rules let the player pass
+            if let Some(chosen) = move_generator
+                .legal_moves(&self, player)
+                .into_iter()
+                .choose(rng)
+            {
+                self.shift_gate(board, chosen.layer(), chosen.gate());
+                result.push((self, chosen));
+            }
+            player = player.other();
         }
         result
     }
@@ -98,7 +199,7 @@ impl Compact {
             let layer = board.layer(layer_id);
             for gate_id in 0..3 {
                 let gate = layer.gate(gate_id);
-                let mut gatebits = (1 << gate.gatetype()) & 0b111;
+                let mut gatebits = gate_bits(gate.gatetype(), 0);
                 if !gate.topleft() {
                     gatebits = mirror_gates(gatebits);
                 };
@@ -109,9 +210,9 @@ impl Compact {
             }
             layer_bits
         }
-        let mut balls = 0_u64;
+        let mut ball_bits = 0_u64;
         for ball in (0_u8..9).filter(|x| board.ball(*x).is_some()) {
-            balls |= 1 << ball;
+            ball_bits |= 1 << ball;
         }
 
         let mut gates = 0_u64;
@@ -119,10 +220,12 @@ impl Compact {
             gates |= build_layer(board, layer) << (layer * 9);
         }
 
+        let balls = BallBitmask::new(ball_bits);
         let mut result = Self {
-            balls: BallBitmask::new(balls),
+            balls,
             gates,
             gate_shifts: 0,
+            hash: zobrist::state_key(balls, 0),
         };
         result.drop_balls();
         result
@@ -132,11 +235,20 @@ impl Compact {
         let gate_shift_bit_index = (layer * 3 + gate) * 2;
         debug_assert!(self.get_shift(layer, gate) < 3);
 
+        self.hash ^= zobrist::shift_step_key(layer, gate, self.get_shift(layer, gate));
         self.gate_shifts += 1 << gate_shift_bit_index;
 
         debug_assert!(self.gate_shifts < (1 << 26));
     }
 
+    fn decrement_gate_shift(&mut self, layer: u8, gate: u8) {
+        let gate_shift_bit_index = (layer * 3 + gate) * 2;
+        debug_assert!(self.get_shift(layer, gate) > 0, "Gate is not shifted");
+
+        self.gate_shifts -= 1 << gate_shift_bit_index;
+        self.hash ^= zobrist::shift_step_key(layer, gate, self.get_shift(layer, gate));
+    }
+
     pub fn shift_gate_raw(&mut self, board: &Board, layer: u8, gate: u8) {
         self.increment_gate_shift(layer, gate);
 
@@ -157,9 +269,56 @@ impl Compact {
         self.gates = gates;
     }
 
-    pub fn shift_gate(&mut self, board: &Board, layer: u8, gate: u8) {
+    /// Reverts a single `shift_gate_raw`, leaving the balls untouched.
+    pub fn unshift_gate_raw(&mut self, board: &Board, layer: u8, gate: u8) {
+        self.decrement_gate_shift(layer, gate);
+
+        let horizontal = board.layer(layer).horizontal();
+        let topleft = board.layer(layer).gate(gate).topleft();
+        let restored_bits = gate_bits(
+            board.layer(layer).gate(gate).gatetype(),
+            self.get_shift(layer, gate),
+        );
+
+        let mut gates = self.gates;
+        gates = if horizontal {
+            gates
+        } else {
+            transpose_gates(gates)
+        };
+        gates = if topleft { gates } else { mirror_gates(gates) };
+
+        let gate_offset = layer * 9 + gate * 3;
+        let gate_mask = 0b111 << gate_offset;
+        gates = (gates & !gate_mask) | (restored_bits << gate_offset);
+
+        gates = if topleft { gates } else { mirror_gates(gates) };
+        gates = if horizontal {
+            gates
+        } else {
+            transpose_gates(gates)
+        };
+
+        self.gates = gates;
+    }
+
+    /// Shifts a gate and lets the balls fall.
+    /// The returned record can be passed to `unshift_gate` to take the move back.
+    pub fn shift_gate(&mut self, board: &Board, layer: u8, gate: u8) -> ShiftRecord {
+        let record = ShiftRecord {
+            layer,
+            gate,
+            balls: self.balls,
+        };
         self.shift_gate_raw(board, layer, gate);
         self.drop_balls();
+        record
+    }
+
+    /// Takes back the shift described by `record`, including every ball that fell because of it.
+    pub fn unshift_gate(&mut self, board: &Board, record: ShiftRecord) {
+        self.unshift_gate_raw(board, record.layer, record.gate);
+        self.set_balls(record.balls);
     }
 
     #[must_use]
@@ -190,6 +349,33 @@ impl Compact {
         two_bit_array_add(self.gate_shifts & silver_mask)
     }
 
+    /// Player whose turn it is, when both players alternated starting with `starting_player`.
+    /// # Errors
+    /// Will error when the shifts of both players cannot stem from alternating turns.
+    pub fn side_to_move(
+        &self,
+        board: &Board,
+        starting_player: Player,
+    ) -> Result<Player, TurnError> {
+        let silver = self.shift_count_silver(board);
+        let gold = self.shift_count() - silver;
+        let (starter_shifts, follower_shifts) = match starting_player {
+            Player::Gold => (gold, silver),
+            Player::Silver => (silver, gold),
+        };
+        if starter_shifts == follower_shifts {
+            Ok(starting_player)
+        } else if starter_shifts == follower_shifts + 1 {
+            Ok(starting_player.other())
+        } else {
+            Err(TurnError::ImpossibleShiftCount {
+                gold,
+                silver,
+                starting_player,
+            })
+        }
+    }
+
     #[must_use]
     pub const fn get_gate_bits(&self) -> u64 {
         self.gates
@@ -200,8 +386,20 @@ impl Compact {
         self.balls.get_mask()
     }
 
+    #[must_use]
+    pub const fn get_shift_bits(&self) -> u64 {
+        self.gate_shifts
+    }
+
     pub fn drop_balls(&mut self) {
-        self.balls.drop(self.gates);
+        let mut balls = self.balls;
+        balls.drop(self.gates);
+        self.set_balls(balls);
+    }
+
+    const fn set_balls(&mut self, balls: BallBitmask) {
+        self.hash ^= zobrist::ball_keys(self.balls.get_mask() ^ balls.get_mask());
+        self.balls = balls;
     }
 }
 
@@ -233,7 +431,7 @@ mod test {
                 Gate::build().s().t().ty(2).finalize(),
             ],
         }
-        .finalize()
+        .finalize_custom_inventory()
         .expect("Could not generate board")
     }
 
@@ -301,4 +499,72 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn side_to_move() {
+        for starting_player in [Player::Gold, Player::Silver] {
+            let board = crate::Board::random();
+            let initial_state = Compact::build_from_board(&board);
+            assert_eq!(
+                initial_state.side_to_move(&board, starting_player),
+                Ok(starting_player)
+            );
+            let game = initial_state.random_game(&board, starting_player);
+            for (ply, &(state, _)) in (1_usize..).zip(&game) {
+                let expected = if ply.is_multiple_of(2) {
+                    starting_player
+                } else {
+                    starting_player.other()
+                };
+                assert_eq!(state.side_to_move(&board, starting_player), Ok(expected));
+                if !ply.is_multiple_of(2) {
+                    state
+                        .side_to_move(&board, starting_player.other())
+                        .expect_err("Only one player can have moved last");
+                }
+            }
+
+            let mut state = initial_state;
+            let first = crate::MoveChecker::new(&board).moves(&state, starting_player)[0];
+            state.shift_gate(&board, first.layer(), first.gate());
+            state.shift_gate(&board, first.layer(), first.gate());
+            state
+                .side_to_move(&board, starting_player)
+                .expect_err("Gate was shifted twice in a row");
+            state
+                .side_to_move(&board, starting_player.other())
+                .expect_err("Gate was shifted twice in a row");
+        }
+    }
+
+    #[test]
+    fn unshift_restores_state() {
+        for i in 0..100 {
+            let starting_player = if i & 1 == 0 {
+                Player::Silver
+            } else {
+                Player::Gold
+            };
+            let board = crate::Board::random();
+            let initial_state = Compact::build_from_board(&board);
+            let game = initial_state.random_game(&board, starting_player);
+
+            let mut before = initial_state;
+            for &(after, played) in &game {
+                let mut raw = before;
+                raw.shift_gate_raw(&board, played.layer(), played.gate());
+                raw.unshift_gate_raw(&board, played.layer(), played.gate());
+                assert_eq!(raw, before);
+
+                let mut state = before;
+                let record = state.shift_gate(&board, played.layer(), played.gate());
+                assert_eq!(state, after);
+                state.unshift_gate(&board, record);
+                assert_eq!(state, before);
+                assert_eq!(state.depth(), before.depth());
+
+                before = after;
+            }
+        }
+    }
 }
@@ -0,0 +1,159 @@
+use crate::{Board, Move, MoveError, Player};
+
+use super::Compact;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A ball that fell to a lower layer because of a move.
+pub struct BallDrop {
+    pub cell: u8,
+    pub player: Player,
+    pub from_depth: u8,
+    /// 4 when the ball fell out of the cube.
+    pub to_depth: u8,
+}
+
+impl BallDrop {
+    #[must_use]
+    pub const fn left_cube(self) -> bool {
+        self.to_depth == 4
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Consequences of shifting a gate.
+pub struct MoveEffect {
+    pub shift: Move,
+    /// Whether each cell covered by the gate is open after the move, from the top or left.
+    pub holes: [bool; 3],
+    /// Balls that fell, ordered by cell.
+    pub drops: Vec<BallDrop>,
+}
+
+impl Compact {
+    /// Shifts a gate like `shift_gate` and reports which holes opened and which balls fell.
+    /// # Errors
+    /// Will error when the gate is already pulled out completely, leaving the state unchanged.
+    pub fn shift_gate_with_effect(
+        &mut self,
+        board: &Board,
+        gate_move: Move,
+    ) -> Result<MoveEffect, MoveError> {
+        let (layer, gate) = (gate_move.layer(), gate_move.gate());
+        if self.get_shift(layer, gate) >= 3 {
+            return Err(MoveError::GateExhausted { layer, gate });
+        }
+        let depths_before = self.depth();
+        self.shift_gate(board, layer, gate);
+        let depths_after = self.depth();
+
+        let drops = (0_u8..)
+            .zip(depths_before.into_iter().zip(depths_after))
+            .filter(|&(_, (before, after))| before != after)
+            .filter_map(|(cell, (from_depth, to_depth))| {
+                Some(BallDrop {
+                    cell,
+                    player: board.ball(cell)?,
+                    from_depth,
+                    to_depth,
+                })
+            })
+            .collect();
+
+        let horizontal = board.layer(layer).horizontal();
+        let holes = [0, 1, 2].map(|i| {
+            let cell = if horizontal {
+                gate * 3 + i
+            } else {
+                i * 3 + gate
+            };
+            self.get_gate_bits() & (1 << (layer * 9 + cell)) != 0
+        });
+
+        Ok(MoveEffect {
+            shift: gate_move,
+            holes,
+            drops,
+        })
+    }
+
+    /// What `shift_gate_with_effect` would do, returning the resulting state instead of changing this one.
+    /// # Errors
+    /// Will error when the gate is already pulled out completely.
+    pub fn preview_move(
+        &self,
+        board: &Board,
+        gate_move: Move,
+    ) -> Result<(Self, MoveEffect), MoveError> {
+        let mut after = *self;
+        let effect = after.shift_gate_with_effect(board, gate_move)?;
+        Ok((after, effect))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::board::notation::example;
+    use crate::{Board, Compact, MoveChecker, MoveError, Player};
+
+    #[test]
+    fn preview_moves() {
+        let board = example::board();
+        let state = Compact::build_from_board(&board);
+        let first = "1a".parse().expect("Move notation is valid");
+        let (after, effect) = state
+            .preview_move(&board, first)
+            .expect("Gate can be shifted");
+        assert_eq!(effect.holes, [false, false, true]);
+        assert!(effect.drops.is_empty());
+        assert_eq!(state, Compact::build_from_board(&board));
+
+        let mut shifted = state;
+        shifted.shift_gate(&board, 0, 0);
+        assert_eq!(after, shifted);
+
+        let mut exhausted = Compact::build_from_board(&board);
+        for _ in 0..3 {
+            exhausted.shift_gate(&board, 3, 1);
+        }
+        assert_eq!(
+            exhausted.preview_move(&board, "4b".parse().expect("Move notation is valid")),
+            Err(MoveError::GateExhausted { layer: 3, gate: 1 })
+        );
+    }
+
+    #[test]
+    fn preview_played_moves() {
+        for _ in 0..100 {
+            let board = Board::random();
+            let checker = MoveChecker::new(&board);
+            let mut state = Compact::build_from_board(&board);
+            for (played, next_move) in state.random_game(&board, Player::Gold) {
+                for candidate in checker
+                    .legal_moves(&state, Player::Gold)
+                    .into_iter()
+                    .chain(checker.legal_moves(&state, Player::Silver))
+                {
+                    let (after, effect) = state
+                        .preview_move(&board, candidate)
+                        .expect("Legal moves can be previewed");
+                    for drop in &effect.drops {
+                        assert_eq!(state.depth()[drop.cell as usize], drop.from_depth);
+                        assert_eq!(after.depth()[drop.cell as usize], drop.to_depth);
+                        assert!(drop.from_depth < drop.to_depth);
+                    }
+                    let moved = (0..9)
+                        .filter(|cell| state.depth()[*cell] != after.depth()[*cell])
+                        .count();
+                    assert_eq!(effect.drops.len(), moved);
+                }
+                let effect = state
+                    .shift_gate_with_effect(&board, next_move)
+                    .expect("Played moves are legal");
+                assert_eq!(state, played);
+                assert_eq!(effect.shift, next_move);
+            }
+        }
+    }
+}
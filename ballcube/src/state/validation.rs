@@ -0,0 +1,199 @@
+use crate::{Board, Player};
+
+use super::{gate_bits, BallBitmask, Compact};
+
+#[derive(thiserror::Error, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateDecodingError {
+    #[error("Ball code {0} does not fit into 9 base-5 digits")]
+    BallCodeOutOfRange(u64),
+    #[error("Empty cell {cell} has depth {depth} instead of 4")]
+    EmptyCellDepth { cell: u8, depth: u8 },
+    #[error("Gate shifts {0:#x} use more than 24 bits")]
+    ShiftBitsOutOfRange(u64),
+    #[error("Gold shifted {gold} times and silver {silver} times, but players take turns")]
+    ShiftCountImbalance { gold: u8, silver: u8 },
+    #[error("Ball in cell {cell} rests at depth {depth} on an open hole")]
+    BallOnOpenHole { cell: u8, depth: u8 },
+    #[error("Ball in cell {cell} cannot have fallen to depth {depth}, a gate above it never had a hole there")]
+    BallUnreachable { cell: u8, depth: u8 },
+}
+
+/// Gate covering `cell` in `layer` and the distance of the cell from the side the gate is pulled from.
+const fn gate_position(board: &Board, layer: u8, cell: u8) -> (u8, u8) {
+    let horizontal = board.layer(layer).horizontal();
+    let (gate, distance) = if horizontal {
+        (cell / 3, cell % 3)
+    } else {
+        (cell % 3, cell / 3)
+    };
+    if board.layer(layer).gate(gate).topleft() {
+        (gate, distance)
+    } else {
+        (gate, 2 - distance)
+    }
+}
+
+const fn hole_open(board: &Board, layer: u8, cell: u8, shift: u8) -> bool {
+    let (gate, position) = gate_position(board, layer, cell);
+    (gate_bits(board.layer(layer).gate(gate).gatetype(), shift) >> position) & 1 == 1
+}
+
+/// Bitmask of the depths a ball in `cell` can rest at,
+/// when the gates covering the cell have been shifted `shifts[layer]` times.
+pub fn possible_depths(board: &Board, cell: u8, shifts: [u8; 4]) -> u8 {
+    let mut result = 0;
+    for (layer, shift) in (0..).zip(shifts) {
+        if !hole_open(board, layer, cell, shift) {
+            result |= 1 << layer;
+        }
+        if !(0..=shift).any(|earlier| hole_open(board, layer, cell, earlier)) {
+            return result;
+        }
+    }
+    result | (1 << 4)
+}
+
+/// Shifts of the gates covering `cell`, one for each layer.
+pub fn cell_shifts(board: &Board, shifts: &[[u8; 3]; 4], cell: u8) -> [u8; 4] {
+    [0, 1, 2, 3].map(|layer| shifts[layer as usize][gate_position(board, layer, cell).0 as usize])
+}
+
+impl Compact {
+    /// Like `from_u64`, but checks that the code describes a state that can occur on `board`.
+    /// # Errors
+    /// Will error when ball depths or gate shifts are out of range or cannot result from playing on `board`.
+    pub fn try_from_u64(int: u64, board: &Board) -> Result<Self, StateDecodingError> {
+        let mut ball_bits = int & ((1_u64 << BallBitmask::COMPRESSED_BITSIZE) - 1);
+        let gate_shifts = int >> BallBitmask::COMPRESSED_BITSIZE;
+        if ball_bits >= 5_u64.pow(9) {
+            return Err(StateDecodingError::BallCodeOutOfRange(ball_bits));
+        }
+        if gate_shifts >= 1 << 24 {
+            return Err(StateDecodingError::ShiftBitsOutOfRange(gate_shifts));
+        }
+
+        let mut depths = [0; 9];
+        for (cell, depth) in (0_u8..).zip(depths.iter_mut()) {
+            *depth = (ball_bits % 5) as u8;
+            ball_bits /= 5;
+            if board.ball(cell).is_none() && *depth != 4 {
+                return Err(StateDecodingError::EmptyCellDepth {
+                    cell,
+                    depth: *depth,
+                });
+            }
+        }
+
+        let shifts = [0_u8, 1, 2, 3].map(|layer| {
+            [0_u8, 1, 2].map(|gate| ((gate_shifts >> ((layer * 3 + gate) * 2)) & 0b11) as u8)
+        });
+        let (mut gold, mut silver) = (0_u8, 0_u8);
+        for layer in 0..4 {
+            for gate in 0..3 {
+                match board.layer(layer).gate(gate).owner() {
+                    Player::Gold => gold += shifts[layer as usize][gate as usize],
+                    Player::Silver => silver += shifts[layer as usize][gate as usize],
+                }
+            }
+        }
+        if gold.abs_diff(silver) > 1 {
+            return Err(StateDecodingError::ShiftCountImbalance { gold, silver });
+        }
+
+        for (cell, depth) in (0_u8..).zip(depths) {
+            if board.ball(cell).is_none() {
+                continue;
+            }
+            let ball_shifts = cell_shifts(board, &shifts, cell);
+            if depth < 4 && hole_open(board, depth, cell, ball_shifts[depth as usize]) {
+                return Err(StateDecodingError::BallOnOpenHole { cell, depth });
+            }
+            if possible_depths(board, cell, ball_shifts) & (1 << depth) == 0 {
+                return Err(StateDecodingError::BallUnreachable { cell, depth });
+            }
+        }
+
+        Ok(Self::from_parts(board, depths, shifts))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::StateDecodingError;
+    use crate::state::BallBitmask;
+    use crate::{Board, Compact, Player};
+
+    fn encode(depths: [u8; 9], gate_shifts: u64) -> u64 {
+        let ball_bits = depths
+            .iter()
+            .rev()
+            .fold(0, |code, depth| code * 5 + u64::from(*depth));
+        ball_bits | (gate_shifts << BallBitmask::COMPRESSED_BITSIZE)
+    }
+
+    #[test]
+    fn decode_valid_states() {
+        for _ in 0..100 {
+            let board = Board::random();
+            let initial_state = Compact::build_from_board(&board);
+            for (state, _) in initial_state.random_game(&board, Player::Gold) {
+                assert_eq!(Compact::try_from_u64(u64::from(&state), &board), Ok(state));
+            }
+        }
+    }
+
+    #[test]
+    fn reject_invalid_states() {
+        let board = Board::random();
+        let state = Compact::build_from_board(&board);
+        let depths = state.depth();
+        let empty_cell = (0..9)
+            .find(|cell| board.ball(*cell).is_none())
+            .expect("Board has an empty cell");
+
+        assert_eq!(
+            Compact::try_from_u64(u64::from(&state) | (1 << 50), &board),
+            Err(StateDecodingError::ShiftBitsOutOfRange(1 << 29))
+        );
+        assert_eq!(
+            Compact::try_from_u64(5_u64.pow(9), &board),
+            Err(StateDecodingError::BallCodeOutOfRange(5_u64.pow(9)))
+        );
+
+        let mut wrong_empty = depths;
+        wrong_empty[empty_cell as usize] = 0;
+        assert_eq!(
+            Compact::try_from_u64(encode(wrong_empty, 0), &board),
+            Err(StateDecodingError::EmptyCellDepth {
+                cell: empty_cell,
+                depth: 0
+            })
+        );
+
+        // Before any shift, every ball rests on the first closed hole below it
+        for cell in (0..9).filter(|cell| *cell != empty_cell) {
+            for depth in (0..5).filter(|depth| *depth != depths[cell as usize]) {
+                let mut moved = depths;
+                moved[cell as usize] = depth;
+                let error = Compact::try_from_u64(encode(moved, 0), &board);
+                assert!(
+                    matches!(
+                        error,
+                        Err(StateDecodingError::BallOnOpenHole { .. }
+                            | StateDecodingError::BallUnreachable { .. })
+                    ),
+                    "{error:?}"
+                );
+            }
+        }
+
+        let mut imbalanced = state;
+        let first = crate::MoveChecker::new(&board).moves(&state, Player::Gold)[0];
+        imbalanced.shift_gate(&board, first.layer(), first.gate());
+        imbalanced.shift_gate(&board, first.layer(), first.gate());
+        assert_eq!(
+            Compact::try_from_u64(u64::from(&imbalanced), &board),
+            Err(StateDecodingError::ShiftCountImbalance { gold: 2, silver: 0 })
+        );
+    }
+}
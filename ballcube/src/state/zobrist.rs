@@ -0,0 +1,145 @@
+//! Fixed random keys for Zobrist hashing of positions.
+//!
+//! A state hashes to the xor of the keys of every ball bit and of the shift count of every gate,
+//! so a move only has to xor out the keys that changed.
+
+use crate::{Board, Player};
+
+use super::{BallBitmask, Compact};
+
+const fn splitmix64(mut value: u64) -> u64 {
+    value = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    value ^ (value >> 31)
+}
+
+/// Key for each bit of the ball mask, a ball at depth `d` in cell `c` uses bit `d * 9 + c`.
+const BALL_KEYS: [u64; 45] = {
+    let mut keys = [0; 45];
+    let mut i = 0;
+    while i < keys.len() {
+        keys[i] = splitmix64(i as u64);
+        i += 1;
+    }
+    keys
+};
+
+/// Key for each gate `layer * 3 + gate` and each number of shifts it can have.
+const SHIFT_KEYS: [[u64; 4]; 12] = {
+    let mut keys = [[0; 4]; 12];
+    let mut i = 0;
+    while i < 12 * 4 {
+        keys[i / 4][i % 4] = splitmix64((BALL_KEYS.len() + i) as u64);
+        i += 1;
+    }
+    keys
+};
+
+/// Key mixed in when silver is on the move.
+const SILVER_TO_MOVE_KEY: u64 = splitmix64(0x5111_7E12);
+/// Separates the board keys from the state keys.
+const BOARD_SALT: u64 = splitmix64(0x000B_0A2D);
+
+/// Xor of the keys of all set bits of `changed_balls`.
+pub const fn ball_keys(mut changed_balls: u64) -> u64 {
+    let mut result = 0;
+    while changed_balls != 0 {
+        result ^= BALL_KEYS[changed_balls.trailing_zeros() as usize];
+        changed_balls &= changed_balls - 1;
+    }
+    result
+}
+
+/// Difference between the keys of a gate shifted `shift` and `shift + 1` times.
+pub const fn shift_step_key(layer: u8, gate: u8, shift: u8) -> u64 {
+    let keys = SHIFT_KEYS[(layer * 3 + gate) as usize];
+    keys[shift as usize] ^ keys[shift as usize + 1]
+}
+
+/// Hash of a state computed from scratch.
+pub fn state_key(balls: BallBitmask, gate_shifts: u64) -> u64 {
+    (0..12).fold(ball_keys(balls.get_mask()), |key, gate| {
+        key ^ SHIFT_KEYS[gate][((gate_shifts >> (gate * 2)) & 0b11) as usize]
+    })
+}
+
+impl Board {
+    /// Hash key of the board, computed once per board and passed to `Compact::position_hash`.
+    #[must_use]
+    pub fn zobrist_key(&self) -> u64 {
+        splitmix64(u64::from(self) ^ BOARD_SALT)
+    }
+}
+
+impl Compact {
+    /// Zobrist hash of the balls and gate shifts, kept up to date by every shift.
+    #[must_use]
+    pub const fn zobrist_key(&self) -> u64 {
+        self.hash
+    }
+
+    /// Hash of the full position for transposition tables, combining the key of the board, the state and the player to move.
+    ///
+    /// `board_key` is the `Board::zobrist_key` of the board the state belongs to.
+    #[must_use]
+    pub const fn position_hash(&self, board_key: u64, side_to_move: Player) -> u64 {
+        let side_key = match side_to_move {
+            Player::Gold => 0,
+            Player::Silver => SILVER_TO_MOVE_KEY,
+        };
+        board_key ^ self.hash ^ side_key
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::state_key;
+    use crate::{Board, Compact, Player};
+
+    #[test]
+    fn incremental_hash() {
+        let mut seen = HashMap::new();
+        for _ in 0..100 {
+            let board = Board::random();
+            let board_key = board.zobrist_key();
+            let initial_state = Compact::build_from_board(&board);
+            assert_eq!(
+                initial_state.zobrist_key(),
+                state_key(initial_state.balls, initial_state.gate_shifts)
+            );
+
+            let mut state = initial_state;
+            let mut records = vec![];
+            for (played, next_move) in initial_state.random_game(&board, Player::Gold) {
+                records.push(state.shift_gate(&board, next_move.layer(), next_move.gate()));
+                assert_eq!(state, played);
+                assert_eq!(
+                    state.zobrist_key(),
+                    state_key(state.balls, state.gate_shifts)
+                );
+                assert_eq!(
+                    Compact::from_u64(u64::from(&state), &board).zobrist_key(),
+                    state.zobrist_key()
+                );
+                assert_ne!(
+                    state.position_hash(board_key, Player::Gold),
+                    state.position_hash(board_key, Player::Silver)
+                );
+                let key = (u64::from(&board), u64::from(&state));
+                assert_eq!(
+                    *seen
+                        .entry(state.position_hash(board_key, Player::Gold))
+                        .or_insert(key),
+                    key
+                );
+            }
+            for record in records.into_iter().rev() {
+                state.unshift_gate(&board, record);
+            }
+            assert_eq!(state.zobrist_key(), initial_state.zobrist_key());
+        }
+    }
+}
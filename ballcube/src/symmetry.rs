@@ -0,0 +1,237 @@
+use crate::board::builder::Gate;
+use crate::{Board, BoardBuilder, Compact, Move};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Rotation or reflection of the 3x3 grid, applied to every layer at once.
+pub enum Symmetry {
+    Identity,
+    RotateClockwise,
+    RotateHalf,
+    RotateCounterClockwise,
+    /// Swaps left and right.
+    MirrorColumns,
+    /// Swaps top and bottom.
+    MirrorRows,
+    /// Reflects along the diagonal from the top left to the bottom right.
+    Transpose,
+    /// Reflects along the diagonal from the top right to the bottom left.
+    AntiTranspose,
+}
+
+impl Symmetry {
+    pub const ALL: [Self; 8] = [
+        Self::Identity,
+        Self::RotateClockwise,
+        Self::RotateHalf,
+        Self::RotateCounterClockwise,
+        Self::MirrorColumns,
+        Self::MirrorRows,
+        Self::Transpose,
+        Self::AntiTranspose,
+    ];
+
+    /// The transform that undoes this one.
+    #[must_use]
+    pub const fn inverse(self) -> Self {
+        match self {
+            Self::RotateClockwise => Self::RotateCounterClockwise,
+            Self::RotateCounterClockwise => Self::RotateClockwise,
+            Self::Identity
+            | Self::RotateHalf
+            | Self::MirrorColumns
+            | Self::MirrorRows
+            | Self::Transpose
+            | Self::AntiTranspose => self,
+        }
+    }
+
+    const fn map_coordinates(self, row: u8, column: u8) -> (u8, u8) {
+        match self {
+            Self::Identity => (row, column),
+            Self::RotateClockwise => (column, 2 - row),
+            Self::RotateHalf => (2 - row, 2 - column),
+            Self::RotateCounterClockwise => (2 - column, row),
+            Self::MirrorColumns => (row, 2 - column),
+            Self::MirrorRows => (2 - row, column),
+            Self::Transpose => (column, row),
+            Self::AntiTranspose => (2 - column, 2 - row),
+        }
+    }
+
+    /// Where the cell with index `cell` ends up.
+    #[must_use]
+    pub const fn map_cell(self, cell: u8) -> u8 {
+        let (row, column) = self.map_coordinates(cell / 3, cell % 3);
+        row * 3 + column
+    }
+
+    /// Maps a gate to its new position.
+    /// Returns whether the layer becomes horizontal, the new gate index and whether the gate is pulled from the top or left.
+    const fn map_gate(self, horizontal: bool, gate: u8, topleft: bool) -> (bool, u8, bool) {
+        let (near_end, far_end) = if topleft { (0, 2) } else { (2, 0) };
+        let (handle_cell, opposite_cell) = if horizontal {
+            ((gate, near_end), (gate, far_end))
+        } else {
+            ((near_end, gate), (far_end, gate))
+        };
+        let handle = self.map_coordinates(handle_cell.0, handle_cell.1);
+        let opposite = self.map_coordinates(opposite_cell.0, opposite_cell.1);
+        if handle.0 == opposite.0 {
+            (true, handle.0, handle.1 < opposite.1)
+        } else {
+            (false, handle.1, handle.0 < opposite.0)
+        }
+    }
+
+    /// Maps a move on `board` to the corresponding move on the transformed board.
+    #[must_use]
+    pub const fn apply_move(self, board: &Board, gate_move: Move) -> Move {
+        let layer = board.layer(gate_move.layer());
+        let (_, gate, _) = self.map_gate(
+            layer.horizontal(),
+            gate_move.gate(),
+            layer.gate(gate_move.gate()).topleft(),
+        );
+        Move::new(gate_move.layer(), gate)
+    }
+
+    /// # Panics
+    /// Never.
+    #[must_use]
+    pub fn apply_board(self, board: &Board) -> Board {
+        let original = BoardBuilder::from(board);
+        let mut gates = [None; 12];
+        let mut gates_horizontal = [None; 4];
+        for layer_id in 0..4 {
+            let layer = board.layer(layer_id);
+            for gate_id in 0..3 {
+                let gate = layer.gate(gate_id);
+                let (horizontal, new_gate_id, topleft) =
+                    self.map_gate(layer.horizontal(), gate_id, gate.topleft());
+                gates_horizontal[layer_id as usize] = Some(horizontal);
+                gates[(layer_id * 3 + new_gate_id) as usize] = Some(Gate {
+                    allegiance: gate.owner(),
+                    topleft,
+                    gatetype: gate.gatetype(),
+                });
+            }
+        }
+        BoardBuilder {
+            gold_balls: original
+                .gold_balls
+                .iter()
+                .map(|&cell| self.map_cell(cell))
+                .collect(),
+            silver_balls: original
+                .silver_balls
+                .iter()
+                .map(|&cell| self.map_cell(cell))
+                .collect(),
+            gates_horizontal,
+            gates,
+        }
+        .finalize_custom_inventory()
+        .expect("Transformed board stays valid")
+    }
+
+    /// Transforms a board and a state on it.
+    #[must_use]
+    pub fn apply(self, board: &Board, state: &Compact) -> (Board, Compact) {
+        let new_board = self.apply_board(board);
+
+        let mut depths = [4; 9];
+        for (cell, depth) in (0_u8..).zip(state.depth()) {
+            depths[self.map_cell(cell) as usize] = depth;
+        }
+
+        let mut shifts = [[0; 3]; 4];
+        for layer in 0..4 {
+            for gate in 0..3 {
+                let mapped = self.apply_move(board, Move::new(layer, gate));
+                shifts[layer as usize][mapped.gate() as usize] = state.get_shift(layer, gate);
+            }
+        }
+
+        let new_state = Compact::from_parts(&new_board, depths, shifts);
+        (new_board, new_state)
+    }
+
+    /// Picks a fixed representative among all symmetric variants of a position.
+    /// Returns the representative and the transform that maps the given position onto it.
+    /// # Panics
+    /// Never.
+    #[must_use]
+    pub fn canonicalize(board: &Board, state: &Compact) -> (Board, Compact, Self) {
+        Self::ALL
+            .into_iter()
+            .map(|symmetry| {
+                let (new_board, new_state) = symmetry.apply(board, state);
+                (new_board, new_state, symmetry)
+            })
+            .min_by_key(|candidate| (u64::from(&candidate.0), u64::from(&candidate.1)))
+            .expect("There is always the identity")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Symmetry;
+    use crate::{Board, Compact, Player};
+
+    #[test]
+    fn symmetric_games() {
+        for _ in 0..20 {
+            let board = Board::random();
+            let initial_state = Compact::build_from_board(&board);
+            let game = initial_state.random_game(&board, Player::Gold);
+
+            for symmetry in Symmetry::ALL {
+                let inverse = symmetry.inverse();
+                let (new_board, mut new_state) = symmetry.apply(&board, &initial_state);
+                assert_eq!(inverse.apply_board(&new_board), board);
+
+                for &(state, played) in &game {
+                    let new_move = symmetry.apply_move(&board, played);
+                    assert_eq!(
+                        new_board
+                            .layer(new_move.layer())
+                            .gate(new_move.gate())
+                            .owner(),
+                        board.layer(played.layer()).gate(played.gate()).owner()
+                    );
+                    new_state.shift_gate(&new_board, new_move.layer(), new_move.gate());
+                    assert_eq!(
+                        symmetry.apply(&board, &state),
+                        (new_board.clone(), new_state)
+                    );
+                    assert_eq!(
+                        inverse.apply(&new_board, &new_state),
+                        (board.clone(), state)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn canonical_representative() {
+        let board = Board::random();
+        let mut state = Compact::build_from_board(&board);
+        state.shift_gate(&board, 1, 2);
+        let (canonical_board, canonical_state, used) = Symmetry::canonicalize(&board, &state);
+        assert_eq!(
+            used.apply(&board, &state),
+            (canonical_board.clone(), canonical_state)
+        );
+
+        for symmetry in Symmetry::ALL {
+            let (new_board, new_state) = symmetry.apply(&board, &state);
+            let (found_board, found_state, _) = Symmetry::canonicalize(&new_board, &new_state);
+            assert_eq!(
+                (found_board, found_state),
+                (canonical_board.clone(), canonical_state)
+            );
+        }
+    }
+}
@@ -1,111 +1,418 @@
+//! Text diagrams of a state, one block per layer from top to bottom, followed by a block of the fallen balls.
+//!
+//! Cells show `G`/`S` for a ball resting on the layer, `F`/`f` for a gold/silver ball above an open hole,
+//! `X` for a closed hole without a ball and `O` for an open hole.
+//! The gates are marked on the side they are pulled from with their owner `g` or `s`,
+//! while an arrow on the opposite side points along the pulling direction.
+//!
+//! In the `Ascii` and `Ansi` styles every marker is three characters wide and also holds the gate type and shift,
+//! like `g12` for a gold gate of type 1 shifted twice, so the diagram describes board and state completely
+//! and can be read back with `parse_state_diagram`.
+//! The `Unicode` style marks the shift by underlining and striking through the owner instead.
+
+use core::fmt::{self, Display};
+
 use crate::{state::Compact, Board, Player};
 
-pub fn visualize_state(board: &Board, state: &Compact) {
-    let first_row_char = "_";
-    let last_row_char = "\u{203e}";
-    let first_column_char = "|";
-    let last_column_char = "|";
-    let gold_char = "g";
-    let silver_char = "s";
-
-    let bottom_opposite = "\u{2191}";
-    let top_opposite = "\u{2193}";
-    let left_opposite = "\u{2192}";
-    let right_opposite = "\u{2190}";
-
-    // let corners_tl_tr_bl_br = ["┘", "└", "┐", "┌"];
-    let corners_tl_tr_bl_br = [" ", " ", " ", " "];
-
-    let ball_char_gold = "G";
-    let ball_char_silver = "S";
-    let falling_ball_char_gold = "F";
-    let falling_ball_char_silver = "f";
-    let blocked_char = "X";
-    let open_char = "O";
-
-    let shift_text_modifiers = [
-        "",
-        "\u{0332}",
-        "\u{0332}\u{0305}",
-        "\u{0332}\u{0305}\u{0336}",
-    ];
-
-    let mut result_lines: [String; 5] = Default::default();
-
-    let ball_depth = state.depth();
-
-    let get_side_char = |layer: u8, gate: u8| -> String {
-        let owner_char = match board.layer(layer).gate(gate).owner() {
-            crate::Player::Gold => gold_char,
-            crate::Player::Silver => silver_char,
-        };
-        let char_styling = shift_text_modifiers[state.get_shift(layer, gate) as usize];
-        format!("{owner_char}{char_styling}")
-    };
-
-    for layer in 0..4 {
-        let mut first_column = [(); 3].map(|_| first_column_char.to_owned());
-        let mut last_column = [(); 3].map(|_| last_column_char.to_owned());
-        let mut first_row = [(); 3].map(|_| first_row_char.to_owned());
-        let mut last_row = [(); 3].map(|_| last_row_char.to_owned());
-        let mut field: [String; 9] = Default::default();
-
-        let (tl_side, br_side, tl_opp, br_opp) = if board.layer(layer).horizontal() {
-            (
-                &mut first_column,
-                &mut last_column,
-                left_opposite,
-                right_opposite,
-            )
-        } else {
-            (&mut first_row, &mut last_row, top_opposite, bottom_opposite)
-        };
-
-        for (gate, (tl, br)) in (0_u8..).zip(tl_side.iter_mut().zip(br_side.iter_mut())) {
-            if board.layer(layer).gate(gate).topleft() {
-                *tl = get_side_char(layer, gate);
-                *br = br_opp.to_owned();
-            } else {
-                *tl = tl_opp.to_owned();
-                *br = get_side_char(layer, gate);
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RenderStyle {
+    /// One character per field, shifts shown with combining characters.
+    #[default]
+    Unicode,
+    /// Plain ASCII with three characters per field.
+    Ascii,
+    /// Like `Ascii`, with gold and silver coloured by terminal escape codes.
+    Ansi,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Arrow {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Field {
+    Corner,
+    HorizontalEdge {
+        top: bool,
+    },
+    VerticalEdge,
+    Marker {
+        owner: Player,
+        gatetype: u8,
+        shift: u8,
+    },
+    Arrow(Arrow),
+    Ball(Player),
+    FallingBall(Player),
+    Blocked,
+    Open,
+    Fallen(Option<Player>),
+}
+
+const GOLD_COLOR: &str = "\u{1b}[33m";
+const SILVER_COLOR: &str = "\u{1b}[36m";
+const RESET_COLOR: &str = "\u{1b}[0m";
+
+impl RenderStyle {
+    const fn field_width(self) -> usize {
+        match self {
+            Self::Unicode => 1,
+            Self::Ascii | Self::Ansi => 3,
+        }
+    }
+
+    fn unicode_field(field: Field) -> String {
+        const SHIFT_TEXT_MODIFIERS: [&str; 4] = [
+            "",
+            "\u{0332}",
+            "\u{0332}\u{0305}",
+            "\u{0332}\u{0305}\u{0336}",
+        ];
+        match field {
+            Field::Corner => " ".to_owned(),
+            Field::HorizontalEdge { top: true } => "_".to_owned(),
+            Field::HorizontalEdge { top: false } => "\u{203e}".to_owned(),
+            Field::VerticalEdge => "|".to_owned(),
+            Field::Marker { owner, shift, .. } => {
+                let owner_char = match owner {
+                    Player::Gold => 'g',
+                    Player::Silver => 's',
+                };
+                format!("{owner_char}{}", SHIFT_TEXT_MODIFIERS[shift as usize])
+            }
+            Field::Arrow(Arrow::Right) => "\u{2192}".to_owned(),
+            Field::Arrow(Arrow::Left) => "\u{2190}".to_owned(),
+            Field::Arrow(Arrow::Down) => "\u{2193}".to_owned(),
+            Field::Arrow(Arrow::Up) => "\u{2191}".to_owned(),
+            other @ (Field::Ball(_)
+            | Field::FallingBall(_)
+            | Field::Blocked
+            | Field::Open
+            | Field::Fallen(_)) => Self::cell_char(other).to_string(),
+        }
+    }
+
+    fn ascii_field(field: Field) -> String {
+        match field {
+            Field::Corner => "   ".to_owned(),
+            Field::HorizontalEdge { .. } => "---".to_owned(),
+            Field::VerticalEdge => " | ".to_owned(),
+            Field::Marker {
+                owner,
+                gatetype,
+                shift,
+            } => {
+                let owner_char = match owner {
+                    Player::Gold => 'g',
+                    Player::Silver => 's',
+                };
+                format!("{owner_char}{gatetype}{shift}")
+            }
+            Field::Arrow(Arrow::Right) => " > ".to_owned(),
+            Field::Arrow(Arrow::Left) => " < ".to_owned(),
+            Field::Arrow(Arrow::Down) => " v ".to_owned(),
+            Field::Arrow(Arrow::Up) => " ^ ".to_owned(),
+            other @ (Field::Ball(_)
+            | Field::FallingBall(_)
+            | Field::Blocked
+            | Field::Open
+            | Field::Fallen(_)) => {
+                format!(" {} ", Self::cell_char(other))
+            }
+        }
+    }
+
+    const fn cell_char(field: Field) -> char {
+        match field {
+            Field::Ball(Player::Gold) | Field::Fallen(Some(Player::Gold)) => 'G',
+            Field::Ball(Player::Silver) | Field::Fallen(Some(Player::Silver)) => 'S',
+            Field::FallingBall(Player::Gold) => 'F',
+            Field::FallingBall(Player::Silver) => 'f',
+            Field::Blocked => 'X',
+            Field::Open => 'O',
+            Field::Fallen(None)
+            | Field::Corner
+            | Field::HorizontalEdge { .. }
+            | Field::VerticalEdge
+            | Field::Marker { .. }
+            | Field::Arrow(_) => '.',
+        }
+    }
+
+    fn field(self, field: Field) -> String {
+        match self {
+            Self::Unicode => Self::unicode_field(field),
+            Self::Ascii => Self::ascii_field(field),
+            Self::Ansi => {
+                let owner = match field {
+                    Field::Marker { owner, .. }
+                    | Field::Ball(owner)
+                    | Field::FallingBall(owner)
+                    | Field::Fallen(Some(owner)) => owner,
+                    Field::Fallen(None)
+                    | Field::Corner
+                    | Field::HorizontalEdge { .. }
+                    | Field::VerticalEdge
+                    | Field::Arrow(_)
+                    | Field::Blocked
+                    | Field::Open => return Self::ascii_field(field),
+                };
+                let color = match owner {
+                    Player::Gold => GOLD_COLOR,
+                    Player::Silver => SILVER_COLOR,
+                };
+                format!("{color}{}{RESET_COLOR}", Self::ascii_field(field))
             }
         }
+    }
+}
+
+/// Empty block with edges above and below when `horizontal`, or to the left and right otherwise.
+const fn frame(horizontal: bool) -> [[Field; 5]; 5] {
+    let corner = Field::Corner;
+    if horizontal {
+        let top = Field::HorizontalEdge { top: true };
+        let bottom = Field::HorizontalEdge { top: false };
+        [
+            [corner, top, top, top, corner],
+            [corner; 5],
+            [corner; 5],
+            [corner; 5],
+            [corner, bottom, bottom, bottom, corner],
+        ]
+    } else {
+        let side = [
+            Field::VerticalEdge,
+            corner,
+            corner,
+            corner,
+            Field::VerticalEdge,
+        ];
+        [[corner; 5], side, side, side, [corner; 5]]
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+/// Diagram of a state on its board, rendered through `Display`.
+pub struct StateView<'board> {
+    board: &'board Board,
+    state: &'board Compact,
+    style: RenderStyle,
+    labels: bool,
+}
+
+impl<'board> StateView<'board> {
+    /// Unicode diagram without labels.
+    #[must_use]
+    pub const fn new(board: &'board Board, state: &'board Compact) -> Self {
+        Self {
+            board,
+            state,
+            style: RenderStyle::Unicode,
+            labels: false,
+        }
+    }
 
-        for (id, (bd, cell)) in (0_u8..).zip(ball_depth.iter().zip(field.iter_mut())) {
-            let ball_present = bd == &layer;
-            let hole_present = state.get_gate_bits() & (1 << (9 * layer + id)) > 0;
-            let ball_color = if ball_present { board.ball(id) } else { None };
-            *cell = match (hole_present, ball_color) {
-                (false, Some(Player::Gold)) => ball_char_gold,
-                (false, Some(Player::Silver)) => ball_char_silver,
-                (false, None) => blocked_char,
-                (true, Some(Player::Gold)) => falling_ball_char_gold,
-                (true, Some(Player::Silver)) => falling_ball_char_silver,
-                (true, None) => open_char,
+    #[must_use]
+    pub const fn style(mut self, style: RenderStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Adds the layer number above every block and the letters of rows and columns,
+    /// which match the gate letters of moves.
+    #[must_use]
+    pub const fn labels(mut self, labels: bool) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// Fields of a layer block, row by row including the frame.
+    fn layer_fields(&self, layer: u8) -> [[Field; 5]; 5] {
+        let horizontal = self.board.layer(layer).horizontal();
+        let mut fields = frame(horizontal);
+
+        for gate in 0..3 {
+            let gate_proxy = self.board.layer(layer).gate(gate);
+            let marker = Field::Marker {
+                owner: gate_proxy.owner(),
+                gatetype: gate_proxy.gatetype(),
+                shift: self.state.get_shift(layer, gate),
+            };
+            let (topleft, bottomright) = match (horizontal, gate_proxy.topleft()) {
+                (true, true) => (marker, Field::Arrow(Arrow::Left)),
+                (true, false) => (Field::Arrow(Arrow::Right), marker),
+                (false, true) => (marker, Field::Arrow(Arrow::Up)),
+                (false, false) => (Field::Arrow(Arrow::Down), marker),
+            };
+            let i = gate as usize + 1;
+            if horizontal {
+                fields[i][0] = topleft;
+                fields[i][4] = bottomright;
+            } else {
+                fields[0][i] = topleft;
+                fields[4][i] = bottomright;
             }
-            .to_owned();
         }
 
-        result_lines[0] += &format!(
-            "{}{}{} ",
-            corners_tl_tr_bl_br[0],
-            first_row.join(""),
-            corners_tl_tr_bl_br[1]
-        );
-        for i in 0..3 {
-            result_lines[i + 1] += &format!(
-                "{}{}{} ",
-                first_column[i],
-                field[(i * 3)..((i + 1) * 3)].join(""),
-                last_column[i]
+        let depths = self.state.depth();
+        for cell in 0..9 {
+            let hole_open = self.state.get_gate_bits() & (1 << (9 * layer + cell)) != 0;
+            let ball = if depths[cell as usize] == layer {
+                self.board.ball(cell)
+            } else {
+                None
+            };
+            fields[cell as usize / 3 + 1][cell as usize % 3 + 1] = match (hole_open, ball) {
+                (false, Some(player)) => Field::Ball(player),
+                (false, None) => Field::Blocked,
+                (true, Some(player)) => Field::FallingBall(player),
+                (true, None) => Field::Open,
+            };
+        }
+        fields
+    }
+
+    /// Fields of the block listing the balls that fell through all layers.
+    fn fallen_fields(&self) -> [[Field; 5]; 5] {
+        let mut fields = frame(true);
+        let depths = self.state.depth();
+        for cell in 0..9 {
+            let ball = if depths[cell as usize] == 4 {
+                self.board.ball(cell)
+            } else {
+                None
+            };
+            fields[cell as usize / 3 + 1][cell as usize % 3 + 1] = Field::Fallen(ball);
+        }
+        fields
+    }
+
+    /// The diagram as lines without trailing whitespace.
+    #[must_use]
+    pub fn render(&self) -> String {
+        let width = self.style.field_width();
+        let blocks = [0, 1, 2, 3]
+            .map(|layer| self.layer_fields(layer))
+            .into_iter()
+            .chain([self.fallen_fields()])
+            .collect::<Vec<_>>();
+
+        let mut lines = vec![];
+        if self.labels {
+            let headings = (1..=4)
+                .map(|layer| {
+                    if width == 1 {
+                        format!("L{layer}")
+                    } else {
+                        format!("Layer {layer}")
+                    }
+                })
+                .chain(["Out".to_owned()])
+                .map(|heading| format!("{heading:^block_width$}", block_width = width * 5))
+                .collect::<Vec<_>>();
+            lines.push(format!("  {}", headings.join(" ")).trim_end().to_owned());
+
+            let column_labels = format!(
+                "{:width$}{:^width$}{:^width$}{:^width$}{:width$}",
+                "", "a", "b", "c", ""
+            );
+            lines.push(
+                format!("  {}", vec![column_labels; blocks.len()].join(" "))
+                    .trim_end()
+                    .to_owned(),
             );
         }
-        result_lines[4] += &format!(
-            "{}{}{} ",
-            corners_tl_tr_bl_br[2],
-            last_row.join(""),
-            corners_tl_tr_bl_br[3]
-        );
+        for row in 0..5 {
+            let mut line = if self.labels {
+                match row {
+                    1..=3 => format!("{} ", char::from(b'a' + row as u8 - 1)),
+                    _ => "  ".to_owned(),
+                }
+            } else {
+                String::new()
+            };
+            let block_rows = blocks
+                .iter()
+                .map(|block| {
+                    block[row]
+                        .iter()
+                        .map(|field| self.style.field(*field))
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>();
+            line += &block_rows.join(" ");
+            lines.push(line.trim_end().to_owned());
+        }
+        lines.join("\n")
+    }
+}
+
+impl Display for StateView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render())
+    }
+}
+
+/// Prints the Unicode diagram of the state to stdout.
+#[expect(
+    clippy::print_stdout,
+    reason = "Printing the diagram is what this function is for"
+)]
+pub fn visualize_state(board: &Board, state: &Compact) {
+    println!("{}", StateView::new(board, state));
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RenderStyle, StateView};
+    use crate::board::notation::example;
+    use crate::Compact;
+
+    #[test]
+    fn render_styles() {
+        let board = example::board();
+        let mut state = Compact::build_from_board(&board);
+        state.shift_gate(&board, 0, 0);
+        state.shift_gate(&board, 1, 2);
+
+        let ascii = StateView::new(&board, &state)
+            .style(RenderStyle::Ascii)
+            .labels(true)
+            .to_string();
+        let expected = [
+            "      Layer 1         Layer 2         Layer 3         Layer 4           Out",
+            "      a  b  c         a  b  c         a  b  c         a  b  c         a  b  c",
+            "     ---------        v g30 v        ---------        v g00 v        ---------",
+            "a g01 X  G  O  <   |  O  X  O  |  s30 G  X  X  <   |  X  O  O  |      .  .  .",
+            "b  >  S  S  G s30  |  X  X  X  |  g10 X  O  X  <   |  X  X  X  |      .  .  .",
+            "c s10 S  O  S  <   |  X  G  X  |   >  X  X  O s00  |  X  X  X  |      .  .  .",
+            "     ---------       g20 ^ s01       ---------       g30 ^ s20       ---------",
+        ];
+        assert_eq!(ascii, expected.join("\n"));
+
+        let ansi = StateView::new(&board, &state)
+            .style(RenderStyle::Ansi)
+            .labels(true)
+            .to_string();
+        assert!(ansi.contains("\u{1b}[33mg01\u{1b}[0m"));
+        let stripped = ansi
+            .replace("\u{1b}[33m", "")
+            .replace("\u{1b}[36m", "")
+            .replace("\u{1b}[0m", "");
+        assert_eq!(stripped, ascii);
+
+        let unicode = StateView::new(&board, &state).to_string();
+        assert_eq!(unicode.lines().count(), 5);
+        assert!(unicode
+            .lines()
+            .nth(1)
+            .expect("Diagram has 5 lines")
+            .starts_with("g\u{332}XGO\u{2190} |OXO|"));
     }
-    println!("{}", result_lines.join("\n"));
 }
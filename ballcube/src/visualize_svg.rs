@@ -0,0 +1,269 @@
+//! SVG diagrams of a state, drawing the four layers from left to right followed by a panel of the fallen balls.
+//!
+//! Every gate is drawn as a bar that slides one cell towards its handle per shift,
+//! with the hole of its type cut into it, so the cells left uncovered are open.
+//! Balls are drawn in the layer they rest on, balls that fell out of the cube are marked fallen with a dashed outline.
+//! Once the game is over, the balls left in the cube are stuck and crossed out.
+
+#![expect(
+    clippy::float_arithmetic,
+    reason = "Positions are computed in fractions of cells"
+)]
+#![expect(
+    clippy::format_push_string,
+    reason = "Elements are appended to the image one at a time"
+)]
+
+use crate::{state::Compact, Board, Player, Ruleset, Winner, WinningChecker};
+
+const CELL: i32 = 40;
+/// Room around the grid for bars that are shifted out completely.
+const MARGIN: i32 = 7 * CELL / 2;
+const PANEL: i32 = 3 * CELL + 2 * MARGIN;
+const TITLE: i32 = CELL;
+
+const fn color(player: Player) -> &'static str {
+    match player {
+        Player::Gold => "#d4a017",
+        Player::Silver => "#9ea7b3",
+    }
+}
+
+const fn class(player: Player) -> &'static str {
+    match player {
+        Player::Gold => "gold",
+        Player::Silver => "silver",
+    }
+}
+
+/// Top left corner of the cell in column `x` and row `y` of a panel, where fractions of cells are allowed.
+fn cell_origin(x: f64, y: f64) -> (f64, f64) {
+    (
+        x.mul_add(f64::from(CELL), f64::from(MARGIN)),
+        y.mul_add(f64::from(CELL), f64::from(MARGIN + TITLE)),
+    )
+}
+
+fn draw_grid(svg: &mut String, title: &str) {
+    svg.push_str(&format!(
+        r#"<text x="{}" y="{}" font-family="sans-serif" font-size="20" text-anchor="middle">{title}</text>"#,
+        PANEL / 2,
+        TITLE - 10
+    ));
+    for cell in 0_u8..9 {
+        let (x, y) = cell_origin(f64::from(cell % 3), f64::from(cell / 3));
+        svg.push_str(&format!(
+            r##"<rect class="cell" x="{x}" y="{y}" width="{CELL}" height="{CELL}" fill="#f4f4f4" stroke="#999"/>"##
+        ));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BallMark {
+    None,
+    Fallen,
+    Stuck,
+}
+
+fn draw_ball(svg: &mut String, cell: u8, player: Player, mark: BallMark) {
+    let (x, y) = cell_origin(f64::from(cell % 3) + 0.5, f64::from(cell / 3) + 0.5);
+    let (extra_class, dash) = match mark {
+        BallMark::None => ("", ""),
+        BallMark::Fallen => (" fallen", r#" stroke-dasharray="4 3""#),
+        BallMark::Stuck => (" stuck", ""),
+    };
+    svg.push_str(&format!(
+        r##"<circle class="ball {}{extra_class}" cx="{x}" cy="{y}" r="{}" fill="{}" stroke="#222" stroke-width="2"{dash}/>"##,
+        class(player),
+        f64::from(CELL) * 0.35,
+        color(player)
+    ));
+    if mark == BallMark::Stuck {
+        let radius = f64::from(CELL) * 0.25;
+        svg.push_str(&format!(
+            r##"<path d="M {} {} l {} {} m 0 {} l {} {}" stroke="#222" stroke-width="2"/>"##,
+            x - radius,
+            y - radius,
+            2.0 * radius,
+            2.0 * radius,
+            -2.0 * radius,
+            -2.0 * radius,
+            2.0 * radius
+        ));
+    }
+}
+
+fn draw_gate(svg: &mut String, board: &Board, state: &Compact, layer: u8, gate: u8) {
+    let horizontal = board.layer(layer).horizontal();
+    let gate_proxy = board.layer(layer).gate(gate);
+    let shift = i32::from(state.get_shift(layer, gate));
+    let owner = gate_proxy.owner();
+
+    // Positions count cells from the handle, the bar covers positions `-shift` up to `3 - shift`
+    let to_grid = |position: f64| {
+        let along = if gate_proxy.topleft() {
+            position
+        } else {
+            3.0 - position
+        };
+        if horizontal {
+            (along, f64::from(gate))
+        } else {
+            (f64::from(gate), along)
+        }
+    };
+    let bar_start = to_grid(f64::from(-shift));
+    let bar_end = to_grid(f64::from(3 - shift));
+    let ((x0, y0), (x1, y1)) = (
+        cell_origin(bar_start.0.min(bar_end.0), bar_start.1.min(bar_end.1)),
+        cell_origin(bar_start.0.max(bar_end.0), bar_start.1.max(bar_end.1)),
+    );
+    let (width, height) = if horizontal {
+        (x1 - x0, f64::from(CELL))
+    } else {
+        (f64::from(CELL), y1 - y0)
+    };
+    svg.push_str(&format!(
+        r##"<rect class="gate {}" x="{x0}" y="{y0}" width="{width}" height="{height}" fill="{}" fill-opacity="0.5" stroke="#333"/>"##,
+        class(owner),
+        color(owner)
+    ));
+
+    let handle = to_grid(f64::from(-shift) - 0.5);
+    let handle_center = cell_origin(
+        handle.0 + if horizontal { 0.0 } else { 0.5 },
+        handle.1 + if horizontal { 0.5 } else { 0.0 },
+    );
+    svg.push_str(&format!(
+        r##"<circle class="handle" cx="{}" cy="{}" r="{}" fill="{}" stroke="#333"/><text x="{}" y="{}" font-family="sans-serif" font-size="12" text-anchor="middle" dominant-baseline="central">{shift}</text>"##,
+        handle_center.0,
+        handle_center.1,
+        f64::from(CELL) * 0.25,
+        color(owner),
+        handle_center.0,
+        handle_center.1
+    ));
+
+    let gatetype = i32::from(gate_proxy.gatetype());
+    if gatetype < 3 {
+        let hole = to_grid(f64::from(gatetype - shift) + 0.5);
+        let hole_center = cell_origin(
+            hole.0 + if horizontal { 0.0 } else { 0.5 },
+            hole.1 + if horizontal { 0.5 } else { 0.0 },
+        );
+        svg.push_str(&format!(
+            r##"<circle class="hole" cx="{}" cy="{}" r="{}" fill="#fff" stroke="#333"/>"##,
+            hole_center.0,
+            hole_center.1,
+            f64::from(CELL) * 0.3
+        ));
+    }
+}
+
+/// Whether the game has ended under `ruleset`, no matter which player is on the move.
+fn game_over(board: &Board, state: &Compact, ruleset: &Ruleset) -> bool {
+    let checker = WinningChecker::with_ruleset(board, ruleset);
+    [Player::Gold, Player::Silver]
+        .into_iter()
+        .all(|player| checker.outcome(state, player) != Winner::None)
+}
+
+/// SVG image of the layers and the fallen balls of a state, balls are marked stuck once the game is over under `ruleset`
+/// whichever player is on the move.
+#[must_use]
+pub fn visualize_svg(board: &Board, state: &Compact, ruleset: &Ruleset) -> String {
+    let width = PANEL * 5;
+    let height = PANEL + TITLE;
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    );
+    let depths = state.depth();
+    let game_over = game_over(board, state, ruleset);
+
+    for layer in 0..4 {
+        svg.push_str(&format!(
+            r#"<g class="layer" transform="translate({} 0)">"#,
+            PANEL * i32::from(layer)
+        ));
+        draw_grid(&mut svg, &format!("Layer {}", layer + 1));
+        for gate in 0..3 {
+            draw_gate(&mut svg, board, state, layer, gate);
+        }
+        for (cell, depth) in (0_u8..).zip(depths) {
+            if depth != layer {
+                continue;
+            }
+            if let Some(player) = board.ball(cell) {
+                let mark = if game_over {
+                    BallMark::Stuck
+                } else {
+                    BallMark::None
+                };
+                draw_ball(&mut svg, cell, player, mark);
+            }
+        }
+        svg += "</g>";
+    }
+
+    svg.push_str(&format!(
+        r#"<g class="fallen" transform="translate({} 0)">"#,
+        PANEL * 4
+    ));
+    draw_grid(&mut svg, "Out");
+    for (cell, depth) in (0_u8..).zip(depths) {
+        if let (4, Some(player)) = (depth, board.ball(cell)) {
+            draw_ball(&mut svg, cell, player, BallMark::Fallen);
+        }
+    }
+    svg += "</g></svg>";
+    svg
+}
+
+#[cfg(test)]
+mod test {
+    use super::visualize_svg;
+    use crate::{Board, Compact, EndCondition, Player, Ruleset};
+
+    #[test]
+    fn svg_elements() {
+        let house_rules = Ruleset {
+            end_condition: EndCondition::BallsOut(1),
+            ..Ruleset::default()
+        };
+        for ruleset in [Ruleset::default(), house_rules] {
+            for _ in 0..10 {
+                svg_elements_in_random_game(&ruleset);
+            }
+        }
+    }
+
+    fn svg_elements_in_random_game(ruleset: &Ruleset) {
+        let board = Board::random();
+        let initial_state = Compact::build_from_board(&board);
+        let mut fallen = 0;
+        for (state, _) in initial_state.random_game(&board, Player::Gold) {
+            let svg = visualize_svg(&board, &state, ruleset);
+            assert!(svg.starts_with("<svg "));
+            assert!(svg.ends_with("</g></svg>"));
+            assert_eq!(svg.matches(r#"class="ball "#).count(), 8);
+            assert_eq!(svg.matches(r#"class="gate "#).count(), 12);
+            assert_eq!(svg.matches(r#"class="ball gold"#).count(), 4);
+            let over = super::game_over(&board, &state, ruleset);
+            assert_eq!(
+                svg.contains(" stuck"),
+                over && state.depth().iter().any(|depth| *depth < 4)
+            );
+
+            fallen = (0..9)
+                .filter(|cell| board.ball(*cell).is_some() && state.depth()[*cell as usize] == 4)
+                .count();
+            let (_, out_panel) = svg
+                .split_once(r#"class="fallen""#)
+                .expect("Out panel exists");
+            assert_eq!(out_panel.matches(r#"class="ball "#).count(), fallen);
+            assert_eq!(svg.matches(" fallen").count(), fallen);
+        }
+        // The random game goes on until all balls of a player are out
+        assert!(fallen >= 4);
+    }
+}
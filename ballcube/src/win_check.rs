@@ -1,20 +1,44 @@
-use crate::{Board, Compact, Player};
+use core::cmp::Ordering;
+
+use crate::{Board, Compact, EndCondition, MoveChecker, NoMovesRule, Player, Ruleset};
 
 pub struct WinningChecker {
     gold_ball_mask: u64,
     silver_ball_mask: u64,
+    end_condition: EndCondition,
+    no_moves: NoMovesRule,
+    move_checker: MoveChecker,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Winner {
     None,
+    /// Draw, both players have the same number of balls out or the rules call a draw when a player cannot move.
     Both,
     One(Player),
 }
 
+impl Winner {
+    /// The outcome after exchanging the colours of both players.
+    #[must_use]
+    pub const fn swap_colors(self) -> Self {
+        match self {
+            Self::One(player) => Self::One(player.other()),
+            Self::None | Self::Both => self,
+        }
+    }
+}
+
 impl WinningChecker {
+    /// Checker for the rules of the original game.
     #[must_use]
     pub fn new(board: &Board) -> Self {
+        Self::with_ruleset(board, &Ruleset::default())
+    }
+
+    #[must_use]
+    pub fn with_ruleset(board: &Board, ruleset: &Ruleset) -> Self {
         let mut gold_ball_mask = 0_u64;
         let mut silver_ball_mask = 0_u64;
 
@@ -36,9 +60,23 @@ impl WinningChecker {
         Self {
             gold_ball_mask,
             silver_ball_mask,
+            end_condition: ruleset.end_condition,
+            no_moves: ruleset.no_moves,
+            move_checker: MoveChecker::new(board),
         }
     }
 
+    /// Number of balls of `player` that fell out of the cube.
+    #[must_use]
+    pub const fn balls_out(&self, state: &Compact, player: Player) -> u8 {
+        let mask = match player {
+            Player::Gold => self.gold_ball_mask,
+            Player::Silver => self.silver_ball_mask,
+        };
+        4 - (state.get_ball_bits() & mask).count_ones() as u8
+    }
+
+    /// Winner once all balls of a player are out, regardless of the end condition of the rules.
     #[must_use]
     pub const fn won(&self, state: &Compact) -> Winner {
         let gw = (state.get_ball_bits() & self.gold_ball_mask) == 0;
@@ -50,4 +88,101 @@ impl WinningChecker {
             (true, true) => Winner::Both,
         }
     }
+
+    /// Outcome under the rules with `side_to_move` on the move.
+    ///
+    /// Once the end condition is met, the player with more balls out wins and equal counts are a draw.
+    /// Otherwise the rule for `side_to_move` having no gate left to shift applies.
+    #[must_use]
+    pub fn outcome(&self, state: &Compact, side_to_move: Player) -> Winner {
+        let gold = self.balls_out(state, Player::Gold);
+        let silver = self.balls_out(state, Player::Silver);
+        let over = gold == 4
+            || silver == 4
+            || match self.end_condition {
+                EndCondition::AllBallsOut => false,
+                EndCondition::BallsOut(count) => gold >= count || silver >= count,
+                EndCondition::ShiftLimit(count) => state.shift_count() >= count,
+            };
+        if over {
+            return match gold.cmp(&silver) {
+                Ordering::Greater => Winner::One(Player::Gold),
+                Ordering::Less => Winner::One(Player::Silver),
+                Ordering::Equal => Winner::Both,
+            };
+        }
+        if !self
+            .move_checker
+            .legal_moves(state, side_to_move)
+            .is_empty()
+        {
+            return Winner::None;
+        }
+        match self.no_moves {
+            NoMovesRule::Pass
+                if !self
+                    .move_checker
+                    .legal_moves(state, side_to_move.other())
+                    .is_empty() =>
+            {
+                Winner::None
+            }
+            NoMovesRule::Loss => Winner::One(side_to_move.other()),
+            NoMovesRule::Pass | NoMovesRule::Draw => Winner::Both,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Winner, WinningChecker};
+    use crate::{Board, Compact, NoMovesRule, Player, Ruleset};
+
+    /// State in which gold has pulled out all gates but the game goes on.
+    fn gold_stuck() -> (Board, Compact) {
+        loop {
+            let board = Board::random();
+            let mut state = Compact::build_from_board(&board);
+            for gate in 0..12 {
+                if board.layer(gate / 3).gate(gate % 3).owner() == Player::Gold {
+                    for _ in 0..3 {
+                        state.shift_gate(&board, gate / 3, gate % 3);
+                    }
+                }
+            }
+            if WinningChecker::new(&board).won(&state) == Winner::None {
+                return (board, state);
+            }
+        }
+    }
+
+    #[test]
+    fn no_moves_rules() {
+        for _ in 0..20 {
+            let (board, state) = gold_stuck();
+            for (no_moves, expected) in [
+                (NoMovesRule::Pass, Winner::None),
+                (NoMovesRule::Loss, Winner::One(Player::Silver)),
+                (NoMovesRule::Draw, Winner::Both),
+            ] {
+                let ruleset = Ruleset {
+                    no_moves,
+                    ..Ruleset::default()
+                };
+                let checker = WinningChecker::with_ruleset(&board, &ruleset);
+                assert_eq!(checker.outcome(&state, Player::Gold), expected);
+                assert_eq!(checker.outcome(&state, Player::Silver), Winner::None);
+            }
+
+            let game = state.random_game(&board, Player::Gold);
+            let &(end, _) = game.last().expect("Silver has moves left");
+            assert_ne!(WinningChecker::new(&board).won(&end), Winner::None);
+            for &(_, played) in &game {
+                assert_eq!(
+                    board.layer(played.layer()).gate(played.gate()).owner(),
+                    Player::Silver
+                );
+            }
+        }
+    }
 }
@@ -1,6 +1,5 @@
-#![allow(dead_code)]
-
-use ballcube::Board;
+use ballcube::{Board, Compact, Player, Ruleset};
+use solver::dfs::{DFSEvaluation, DFSWinFinder};
 
 fn build_shell() -> Option<Board> {
     let mut rl = rustyline::Editor::<()>::new();
@@ -10,10 +9,17 @@ fn build_shell() -> Option<Board> {
         // let readline = rl.readline("\u{1F6E0}> ");
         match readline {
             Ok(line) => {
-                println!("Line: {}", line);
+                let board = match line.trim().parse::<u64>() {
+                    Ok(code) => Board::try_from(code).map_err(ballcube::Error::from),
+                    Err(_) => line.parse::<Board>().map_err(ballcube::Error::from),
+                };
+                match board {
+                    Ok(board) => return Some(board),
+                    Err(err) => println!("{}", err),
+                }
             }
             Err(err) => {
-                println!("Error: {:?}", err);
+                println!("Error: {}", err);
                 break;
             }
         }
@@ -21,27 +27,113 @@ fn build_shell() -> Option<Board> {
     None
 }
 
+fn solve(board: &Board, ruleset: &Ruleset, player: Player) {
+    if let Err(err) = ruleset.check_board(board) {
+        println!("{}", ballcube::Error::from(err));
+        return;
+    }
+    println!("Solving, this may take minutes");
+    let state = Compact::build_from_board(board);
+    let evaluation = DFSWinFinder::with_ruleset(board, ruleset).evaluate(&state, player, true);
+    let outcome = match evaluation {
+        DFSEvaluation::Win(_) => format!("{:?} wins", player),
+        DFSEvaluation::Draw(_) => "Draw".to_owned(),
+        DFSEvaluation::Loss(_) => format!("{:?} wins", player.other()),
+    };
+    let turns = evaluation.moves().turns();
+    let passes = turns.iter().filter(|turn| turn.is_none()).count();
+    println!(
+        "{} in {} turns when {:?} starts, {} of them passes",
+        outcome,
+        turns.len(),
+        player,
+        passes
+    );
+}
+
 fn cli() {
     let mut rl = rustyline::Editor::<()>::new();
+    let mut board = None;
+    let mut ruleset = Ruleset::default();
     loop {
         let readline = rl.readline("> ");
         match readline {
-            Ok(line) => match line.as_str() {
-                "build" => {
-                    build_shell();
+            Ok(line) => match line.split_once(' ').unwrap_or((line.as_str(), "")) {
+                ("build", _) => {
+                    board = build_shell().or(board);
                 }
+                ("rules", "") => {
+                    println!(
+                        "Game ends: {}, player without moves: {}, gates: {:?} {:?}",
+                        ruleset.end_condition,
+                        ruleset.no_moves,
+                        ruleset.gold_gate_types,
+                        ruleset.silver_gate_types
+                    )
+                }
+                ("rules", end_condition) => match end_condition.parse() {
+                    Ok(end_condition) => ruleset.end_condition = end_condition,
+                    Err(err) => println!("{}", ballcube::Error::from(err)),
+                },
+                ("stuck", no_moves) => match no_moves.parse() {
+                    Ok(no_moves) => ruleset.no_moves = no_moves,
+                    Err(err) => println!("{}", ballcube::Error::from(err)),
+                },
+                ("gates", types) => match types.split_whitespace().collect::<Vec<_>>()[..] {
+                    [gold, silver] => match (
+                        Ruleset::parse_gate_types(gold),
+                        Ruleset::parse_gate_types(silver),
+                    ) {
+                        (Ok(gold), Ok(silver)) => {
+                            ruleset.gold_gate_types = gold;
+                            ruleset.silver_gate_types = silver;
+                        }
+                        (Err(err), _) | (_, Err(err)) => {
+                            println!("{}", ballcube::Error::from(err))
+                        }
+                    },
+                    _ => println!(
+                        "Usage: gates <gold types> <silver types>, like `gates 001233 001233`"
+                    ),
+                },
+                ("solve", player) => match (&board, player) {
+                    (None, _) => println!("No board yet, create one with `build`"),
+                    (Some(board), "" | "gold") => solve(board, &ruleset, Player::Gold),
+                    (Some(board), "silver") => solve(board, &ruleset, Player::Silver),
+                    (Some(_), _) => println!("Usage: solve [gold|silver], Gold starts by default"),
+                },
                 _ => {
                     println!("Unknown command: {}", line)
                 }
             },
             Err(err) => {
-                println!("Error: {:?}", err);
+                println!("Error: {}", err);
                 break;
             }
         }
     }
 }
 
+/// Starts the shell, or with `cases [seed]` generates training cases instead
 fn main() {
-    solver::machine_learning::generate_case_list();
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        None => cli(),
+        Some("cases") => {
+            let seed = args
+                .next()
+                .and_then(|arg| arg.parse().ok())
+                .unwrap_or_else(rand::random);
+            println!("Seed: {}", seed);
+            if let Err(err) =
+                solver::machine_learning::generate_case_list(seed, &Ruleset::default())
+            {
+                println!("Error: {}", err);
+            }
+        }
+        Some(arg) => println!(
+            "Unknown argument `{}`, expected none for the shell or `cases [seed]`",
+            arg
+        ),
+    }
 }
@@ -1,9 +1,13 @@
 use crate::island_finder::Island;
 
 use super::move_chain::MoveChain;
-use ballcube::{Board, Compact, Move, MoveChecker, Player, Winner, WinningChecker};
+use ballcube::{
+    Board, Compact, EndCondition, Move, MoveChecker, Player, Ruleset, Winner, WinningChecker,
+    STANDARD_GATE_TYPES,
+};
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DFSEvaluation {
     Win(MoveChain),
     Draw(MoveChain),
@@ -55,7 +59,7 @@ impl PartialEq for DFSEvaluation {
         match (self, other) {
             (Self::Win(l0), Self::Win(r0))
             | (Self::Draw(l0), Self::Draw(r0))
-            | (Self::Loss(l0), Self::Loss(r0)) => l0.moves().len() == r0.moves().len(),
+            | (Self::Loss(l0), Self::Loss(r0)) => l0.turns().len() == r0.turns().len(),
             _ => false,
         }
     }
@@ -66,19 +70,19 @@ impl Ord for DFSEvaluation {
         match self {
             DFSEvaluation::Win(x) => {
                 if let Self::Win(y) = other {
-                    x.moves().len().cmp(&y.moves().len()).reverse()
+                    x.turns().len().cmp(&y.turns().len()).reverse()
                 } else {
                     std::cmp::Ordering::Greater
                 }
             }
             DFSEvaluation::Draw(x) => match other {
                 DFSEvaluation::Win(_y) => std::cmp::Ordering::Less,
-                DFSEvaluation::Draw(y) => x.moves().len().cmp(&y.moves().len()).reverse(),
+                DFSEvaluation::Draw(y) => x.turns().len().cmp(&y.turns().len()).reverse(),
                 DFSEvaluation::Loss(_y) => std::cmp::Ordering::Greater,
             },
             DFSEvaluation::Loss(x) => {
                 if let Self::Loss(y) = other {
-                    x.moves().len().cmp(&y.moves().len())
+                    x.turns().len().cmp(&y.turns().len())
                 } else {
                     std::cmp::Ordering::Less
                 }
@@ -97,55 +101,46 @@ pub struct DFSWinFinder<'a> {
     checker: WinningChecker,
     move_generator: MoveChecker,
     board: &'a Board,
+    /// Islands predict who gets all balls out first, which only decides the game under the original end condition.
+    /// They are only trusted for the standard gate inventory.
+    use_islands: bool,
 }
 
 impl<'a> DFSWinFinder<'a> {
     #[must_use]
     pub fn new(board: &'a Board) -> Self {
-        let checker = WinningChecker::new(board);
+        Self::with_ruleset(board, &Ruleset::default())
+    }
+
+    #[must_use]
+    pub fn with_ruleset(board: &'a Board, ruleset: &Ruleset) -> Self {
+        let checker = WinningChecker::with_ruleset(board, ruleset);
         let move_generator = MoveChecker::new(board);
 
         Self {
             checker,
             move_generator,
             board,
+            use_islands: ruleset.end_condition == EndCondition::AllBallsOut
+                && [Player::Gold, Player::Silver].into_iter().all(|player| {
+                    let mut types = ruleset.gate_types(player);
+                    types.sort_unstable();
+                    types == STANDARD_GATE_TYPES
+                }),
         }
     }
 
-    /// # Panics
-    /// Panics when the state is in an invalid state
-    #[must_use]
-    pub fn evaluate(
-        &self,
-        state: &Compact,
-        player: Player,
-        prune_alpha_beta: bool,
-    ) -> DFSEvaluation {
-        match self.checker.won(state) {
-            Winner::None => (),
-            Winner::Both => return DFSEvaluation::Draw(MoveChain::new(player)),
-            Winner::One(x) if x == player => return DFSEvaluation::Win(MoveChain::new(player)),
-            Winner::One(_) => return DFSEvaluation::Loss(MoveChain::new(player)),
-        };
-
+    /// Player who certainly gets all balls out first, judged by the islands of both players taking turns
+    fn island_winner(&self, state: &Compact, player: Player) -> Option<Player> {
         let islands = super::island_finder::measure_island(self.board, state);
 
-        let starting_player = match state
-            .shift_count()
-            .cmp(&(state.shift_count_silver(self.board) * 2))
-        {
-            std::cmp::Ordering::Less => Player::Gold,
-            std::cmp::Ordering::Equal => player,
-            std::cmp::Ordering::Greater => Player::Silver,
-        };
-
         let has_better_island =
             |checked_player: Player, definite: Option<Island>, heuristic: Option<Island>| {
                 definite.map_or(false, |unwrapped_definite| {
                     heuristic.map_or(true, |unwrapped_heuristic| {
                         unwrapped_definite.distance < unwrapped_heuristic.distance
                             || (unwrapped_definite.distance == unwrapped_heuristic.distance
-                                && starting_player != checked_player)
+                                && player != checked_player)
                     })
                 })
             };
@@ -164,26 +159,56 @@ impl<'a> DFSWinFinder<'a> {
         debug_assert!(!(gold_better_island && silver_better_island));
 
         if gold_better_island {
-            return match player {
-                Player::Gold => DFSEvaluation::Win(MoveChain::new(player)),
-                Player::Silver => DFSEvaluation::Loss(MoveChain::new(player)),
-            };
+            Some(Player::Gold)
+        } else if silver_better_island {
+            Some(Player::Silver)
+        } else {
+            None
         }
-        if silver_better_island {
-            return match player {
-                Player::Gold => DFSEvaluation::Loss(MoveChain::new(player)),
-                Player::Silver => DFSEvaluation::Win(MoveChain::new(player)),
-            };
+    }
+
+    /// Best outcome `player` can force with `player` on the move
+    #[must_use]
+    pub fn evaluate(
+        &self,
+        state: &Compact,
+        player: Player,
+        prune_alpha_beta: bool,
+    ) -> DFSEvaluation {
+        let mut state = *state;
+        self.search(&mut state, player, prune_alpha_beta)
+    }
+
+    /// Like `evaluate`, but plays the moves on `state` and takes them back before returning
+    fn search(&self, state: &mut Compact, player: Player, prune_alpha_beta: bool) -> DFSEvaluation {
+        match self.checker.outcome(state, player) {
+            Winner::None => (),
+            Winner::Both => return DFSEvaluation::Draw(MoveChain::new(player)),
+            Winner::One(x) if x == player => return DFSEvaluation::Win(MoveChain::new(player)),
+            Winner::One(_) => return DFSEvaluation::Loss(MoveChain::new(player)),
         }
 
-        let mut best_option = None;
-        for m in self.move_generator.moves(state, player) {
-            let mut new_state = *state;
-            new_state.shift_gate(self.board, m.layer(), m.gate());
+        let moves = self.move_generator.legal_moves(state, player);
+        let both_can_move = !moves.is_empty()
+            && !self
+                .move_generator
+                .legal_moves(state, player.other())
+                .is_empty();
+        if self.use_islands && both_can_move {
+            if let Some(winner) = self.island_winner(state, player) {
+                return if winner == player {
+                    DFSEvaluation::Win(MoveChain::new(player))
+                } else {
+                    DFSEvaluation::Loss(MoveChain::new(player))
+                };
+            }
+        }
 
-            let mut ev = self
-                .evaluate(&new_state, player.other(), prune_alpha_beta)
-                .flip();
+        let mut best_option = None;
+        for m in moves {
+            let record = state.shift_gate(self.board, m.layer(), m.gate());
+            let mut ev = self.search(state, player.other(), prune_alpha_beta).flip();
+            state.unshift_gate(self.board, record);
             ev.add_move(m);
 
             if let Some(ref b) = best_option {
@@ -200,19 +225,18 @@ impl<'a> DFSWinFinder<'a> {
             }
         }
 
-        if let Some(x) = best_option {
-            x
-        } else {
-            dbg!(state.shift_count(), state.shift_count_silver(self.board));
-            dbg!(state.depth());
-            panic!("No moves but no winner?");
-        }
+        // Without moves the outcome did not end the game, so the rules let the other player move instead
+        best_option.unwrap_or_else(|| {
+            let mut ev = self.search(state, player.other(), prune_alpha_beta).flip();
+            ev.moves_mut().pass();
+            ev
+        })
     }
 }
 
 #[cfg(test)]
 mod test {
-    use ballcube::{visualize_state, Board, Compact, Player};
+    use ballcube::{visualize_state, Board, Compact, MoveChecker, Player};
 
     use crate::dfs::DFSWinFinder;
 
@@ -220,12 +244,23 @@ mod test {
 
     fn check_moves(board: &Board, state: &Compact, moves: &MoveChain) {
         let mut state = *state;
-        for (i, m) in moves.moves().iter().rev().enumerate() {
+        for (i, turn) in moves.turns().iter().rev().enumerate() {
             let current_player = if i % 2 == 0 {
                 moves.starting_player()
             } else {
                 moves.starting_player().other()
             };
+            let Some(m) = turn else {
+                if !MoveChecker::new(board)
+                    .legal_moves(&state, current_player)
+                    .is_empty()
+                {
+                    dbg!(moves, i);
+                    visualize_state(board, &state);
+                    panic!("Player passed with moves left");
+                }
+                continue;
+            };
             if board.layer(m.layer()).gate(m.gate()).owner() != current_player {
                 dbg!(moves, i);
                 visualize_state(board, &state);
@@ -240,6 +275,180 @@ mod test {
         }
     }
 
+    #[test]
+    fn swapped_colors_evaluation() {
+        let board = ballcube::Board::random();
+        let swapped = board.swap_colors();
+        let state = Compact::build_from_board(&board);
+
+        for (ply, (chosen_state, _)) in (1..).zip(state.random_game(&board, Player::Gold)) {
+            let player = if ply % 2 == 0 {
+                Player::Gold
+            } else {
+                Player::Silver
+            };
+            let ev = DFSWinFinder::new(&board).evaluate(&chosen_state, player, true);
+            let swapped_ev =
+                DFSWinFinder::new(&swapped).evaluate(&chosen_state, player.other(), true);
+            assert_eq!(
+                std::mem::discriminant(&ev),
+                std::mem::discriminant(&swapped_ev)
+            );
+        }
+    }
+
+    #[test]
+    fn house_rules_evaluation() {
+        use ballcube::{EndCondition, Game, Ruleset, Winner, WinningChecker};
+        use rand::seq::SliceRandom;
+
+        use crate::dfs::DFSEvaluation;
+
+        // The shift limit keeps the search shallow, other end conditions can take long without islands
+        for end_condition in [EndCondition::ShiftLimit(8), EndCondition::ShiftLimit(10)] {
+            let ruleset = Ruleset {
+                end_condition,
+                ..Ruleset::default()
+            };
+            let board = Board::random();
+            let mut game = Game::with_ruleset(board.clone(), Player::Gold, ruleset.clone());
+            let mut positions = vec![(*game.state(), game.current_player())];
+            while let Some(&m) = game.legal_moves().choose(&mut rand::thread_rng()) {
+                game.apply(m).expect("Legal move was rejected");
+                positions.push((*game.state(), game.current_player()));
+            }
+
+            let finder = DFSWinFinder::with_ruleset(&board, &ruleset);
+            let checker = WinningChecker::with_ruleset(&board, &ruleset);
+            for (state, player) in positions.into_iter().rev().take(7) {
+                let ev = finder.evaluate(&state, player, true);
+                check_moves(&board, &state, ev.moves());
+
+                let mut end = state;
+                for m in ev.moves().moves().iter().rev() {
+                    end.shift_gate(&board, m.layer(), m.gate());
+                }
+                let expected = match ev {
+                    DFSEvaluation::Win(_) => Winner::One(player),
+                    DFSEvaluation::Draw(_) => Winner::Both,
+                    DFSEvaluation::Loss(_) => Winner::One(player.other()),
+                };
+                let side_to_move = if ev.moves().turns().len().is_multiple_of(2) {
+                    player
+                } else {
+                    player.other()
+                };
+                assert_eq!(checker.outcome(&end, side_to_move), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn custom_inventory_evaluation() {
+        use ballcube::{Game, Ruleset, Winner, WinningChecker};
+        use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+        use crate::dfs::DFSEvaluation;
+
+        assert!(DFSWinFinder::new(&Board::random()).use_islands);
+
+        let mut rng = StdRng::seed_from_u64(25);
+        for _ in 0..10 {
+            let ruleset = Ruleset {
+                gold_gate_types: [0, 1, 1, 2, 2, 3],
+                silver_gate_types: std::array::from_fn(|_| rng.gen_range(0..4)),
+                ..Ruleset::default()
+            };
+            let board =
+                Board::random_with_ruleset(&ruleset, &mut rng).expect("Gate types are in range");
+            let mut game = Game::with_ruleset(board.clone(), Player::Gold, ruleset.clone());
+            let mut positions = vec![(*game.state(), game.current_player())];
+            while let Some(&m) = game.legal_moves().choose(&mut rng) {
+                game.apply(m).expect("Legal move was rejected");
+                positions.push((*game.state(), game.current_player()));
+            }
+
+            let finder = DFSWinFinder::with_ruleset(&board, &ruleset);
+            assert!(!finder.use_islands);
+            let checker = WinningChecker::with_ruleset(&board, &ruleset);
+            // Searching without islands is slow, so only the end of the game is evaluated
+            for (state, player) in positions.into_iter().rev().take(6) {
+                let ev = finder.evaluate(&state, player, true);
+                check_moves(&board, &state, ev.moves());
+
+                let mut end = state;
+                for m in ev.moves().moves().iter().rev() {
+                    end.shift_gate(&board, m.layer(), m.gate());
+                }
+                let expected = match ev {
+                    DFSEvaluation::Win(_) => Winner::One(player),
+                    DFSEvaluation::Draw(_) => Winner::Both,
+                    DFSEvaluation::Loss(_) => Winner::One(player.other()),
+                };
+                let side_to_move = if ev.moves().turns().len().is_multiple_of(2) {
+                    player
+                } else {
+                    player.other()
+                };
+                assert_eq!(checker.outcome(&end, side_to_move), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn stuck_player_evaluation() {
+        use ballcube::{NoMovesRule, Ruleset, Winner, WinningChecker};
+
+        use crate::dfs::DFSEvaluation;
+
+        let mut found = 0;
+        while found < 5 {
+            let board = Board::random();
+            let mut state = Compact::build_from_board(&board);
+            for gate in 0..12 {
+                if board.layer(gate / 3).gate(gate % 3).owner() == Player::Gold {
+                    for _ in 0..3 {
+                        state.shift_gate(&board, gate / 3, gate % 3);
+                    }
+                }
+            }
+            // Silver shifting alone keeps the search small
+            if WinningChecker::new(&board).won(&state) != Winner::None
+                || state.shift_count_silver(&board) != 0
+            {
+                continue;
+            }
+            found += 1;
+
+            for no_moves in [NoMovesRule::Pass, NoMovesRule::Loss, NoMovesRule::Draw] {
+                let ruleset = Ruleset {
+                    no_moves,
+                    ..Ruleset::default()
+                };
+                let ev = DFSWinFinder::with_ruleset(&board, &ruleset).evaluate(
+                    &state,
+                    Player::Gold,
+                    true,
+                );
+                assert_eq!(ev.moves().starting_player(), Player::Gold);
+                check_moves(&board, &state, ev.moves());
+                match no_moves {
+                    NoMovesRule::Pass => {
+                        // Gold passes on every turn, so every other turn of the chain is a pass
+                        let turns = ev.moves().turns();
+                        assert!(turns.len() >= 2);
+                        assert_eq!(
+                            turns.iter().filter(|turn| turn.is_none()).count(),
+                            turns.len() / 2
+                        );
+                    }
+                    NoMovesRule::Loss => assert!(matches!(ev, DFSEvaluation::Loss(_))),
+                    NoMovesRule::Draw => assert!(matches!(ev, DFSEvaluation::Draw(_))),
+                }
+            }
+        }
+    }
+
     #[test]
     fn random_evaluation() {
         let board = ballcube::Board::random();
@@ -265,7 +474,7 @@ mod test {
                 "{} for {:#?} in {:02} turns",
                 ev_str,
                 player,
-                ev.moves().moves().len()
+                ev.moves().turns().len()
             );
             visualize_state(&board, &chosen_state);
 
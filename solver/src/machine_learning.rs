@@ -1,21 +1,46 @@
-use ballcube::{Compact, Player};
-use rand::Rng;
-
-fn generate_case(depth: usize) -> String {
-    let board = ballcube::Board::random();
+use ballcube::{Compact, Player, Ruleset, Winner, WinningChecker};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Random games tried before giving up on finding one that lasts long enough
+const ATTEMPTS: usize = 1000;
+
+/// Evaluates a random position `depth` moves into a random game.
+/// The position only depends on `seed` and `ruleset`, the seed is written into the case to regenerate it later.
+/// Gate types of `ruleset` have to be checked beforehand.
+/// Returns `None` when none of the tried games under `ruleset` is still going after `depth` moves.
+fn generate_case(depth: usize, seed: u64, ruleset: &Ruleset) -> Option<String> {
+    generate_case_with_rng(depth, seed, ruleset, &mut StdRng::seed_from_u64(seed))
+}
 
-    let starting_player = if rand::thread_rng().gen() {
-        Player::Silver
-    } else {
-        Player::Gold
-    };
+fn generate_case_with_rng(
+    depth: usize,
+    seed: u64,
+    ruleset: &Ruleset,
+    rng: &mut StdRng,
+) -> Option<String> {
+    let (board, starting_player, state) = (0..ATTEMPTS).find_map(|_| {
+        let board =
+            ballcube::Board::random_with_ruleset(ruleset, rng).expect("Gate types were checked");
+
+        let starting_player = if rng.gen() {
+            Player::Silver
+        } else {
+            Player::Gold
+        };
 
-    let state_list = Compact::build_from_board(&board).random_game(&board, starting_player);
-    let state = if let Some(x) = state_list.get(depth) {
-        x.0
-    } else {
-        return generate_case(depth);
-    };
+        let state_list = Compact::build_from_board(&board).random_game_with_ruleset(
+            &board,
+            starting_player,
+            ruleset,
+            rng,
+        );
+        let (state, _) = *state_list.get(depth)?;
+        let checker = WinningChecker::with_ruleset(&board, ruleset);
+        [Player::Gold, Player::Silver]
+            .into_iter()
+            .all(|player| checker.outcome(&state, player) == Winner::None)
+            .then_some((board, starting_player, state))
+    })?;
 
     let mut result = vec![];
 
@@ -52,87 +77,123 @@ fn generate_case(depth: usize) -> String {
         }
     }
 
-    // First state is where starting player already moved, with index 0
-    let current_player = if depth % 2 == 0 {
-        starting_player.other()
-    } else {
-        starting_player
-    };
+    let current_player = state
+        .side_to_move(&board, starting_player)
+        .expect("Random game alternates players");
     result.push(match current_player {
         Player::Gold => 0,
         Player::Silver => 1,
     });
 
-    if state.shift_count() % 2 != 0 {
-        match current_player {
-            Player::Gold => {
-                debug_assert_eq!(state.shift_count() / state.shift_count_silver(&board), 1);
-            }
-            Player::Silver => {
-                debug_assert_eq!(state.shift_count() / state.shift_count_silver(&board), 2);
-            }
-        }
-    }
-
-    let win = match crate::dfs::DFSWinFinder::new(&board).evaluate(&state, current_player, true) {
+    let win = match crate::dfs::DFSWinFinder::with_ruleset(&board, ruleset).evaluate(
+        &state,
+        current_player,
+        true,
+    ) {
         crate::dfs::DFSEvaluation::Loss(_) => 0,
         crate::dfs::DFSEvaluation::Win(_) => 1,
         crate::dfs::DFSEvaluation::Draw(_) => 2,
     };
     result.push(win);
 
-    format!(
-        "{:#018X}, {:#018X}, {}, {}",
+    Some(format!(
+        "{:#018X}, {:#018X}, {}, {}, {}",
         u64::from(&board),
         u64::from(&state),
         depth + 1,
+        seed,
         result
             .into_iter()
             .map(|x| x.to_string())
             .collect::<Vec<_>>()
             .join(", ")
-    )
+    ))
 }
 
-pub fn generate_case_list() {
-    use std::io::Write;
+/// Appends 1000 evaluated positions to `data.csv`, case `i` is generated from `seed + i`
+/// # Errors
+/// Will error when the ruleset has invalid gate types or ends games too early,
+/// or the file cannot be written or already holds cases with other columns
+pub fn generate_case_list(seed: u64, ruleset: &Ruleset) -> std::io::Result<()> {
+    use std::io::{BufRead, Write};
+
+    ruleset
+        .check_gate_types()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
 
     let mut header_items = vec![];
+    for i in 1..=9 {
+        header_items.push(format!("Ball{i}_Owner"));
+        header_items.push(format!("Ball{i}_Depth"));
+    }
+
+    for layer in 1..=4 {
+        header_items.push(format!("Layer{layer}_Horizontal"));
+        for gate in 1..=3 {
+            header_items.push(format!("Layer{layer}_Gate{gate}_Owner"));
+            header_items.push(format!("Layer{layer}_Gate{gate}_Type"));
+            header_items.push(format!("Layer{layer}_Gate{gate}_Topleft"));
+            header_items.push(format!("Layer{layer}_Gate{gate}_Shift"));
+        }
+    }
+    header_items.push("CurrentPlayer".to_string());
+    header_items.push("CurrentPlayerWin".to_string());
+    let header = format!("Board,State,Depth,Seed,{}", header_items.join(","));
+
     let filename = "data.csv";
     let file_already_exists = std::path::Path::new(filename).exists();
+    if file_already_exists {
+        let mut existing_header = String::new();
+        std::io::BufReader::new(std::fs::File::open(filename)?).read_line(&mut existing_header)?;
+        if existing_header.trim_end() != header {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "{filename} holds cases with other columns, move it away to start a new one"
+                ),
+            ));
+        }
+    }
     let mut output_file = std::fs::OpenOptions::new()
-        .write(true)
         .create(true)
         .append(true)
-        .open(filename)
-        .expect("Could not open file");
+        .open(filename)?;
     if !file_already_exists {
-        for i in 1..=9 {
-            header_items.push(format!("Ball{}_Owner", i));
-            header_items.push(format!("Ball{}_Depth", i));
-        }
-
-        for layer in 1..=4 {
-            header_items.push(format!("Layer{}_Horizontal", layer));
-            for gate in 1..=3 {
-                header_items.push(format!("Layer{}_Gate{}_Owner", layer, gate));
-                header_items.push(format!("Layer{}_Gate{}_Type", layer, gate));
-                header_items.push(format!("Layer{}_Gate{}_Topleft", layer, gate));
-                header_items.push(format!("Layer{}_Gate{}_Shift", layer, gate));
-            }
-        }
-        header_items.push("CurrentPlayer".to_string());
-        header_items.push("CurrentPlayerWin".to_string());
-
-        let header = header_items.join(",");
-
-        writeln!(output_file, "Board,State,Depth,{}", header).expect("Could not write header");
+        writeln!(output_file, "{header}")?;
     }
 
     for i in 0..1000 {
-        writeln!(output_file, "{}", generate_case(14)).expect("Could not write line");
+        let case = generate_case(14, seed.wrapping_add(i), ruleset).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Games under these rules end too early to generate cases",
+            )
+        })?;
+        writeln!(output_file, "{case}")?;
         println!("Wrote case #{:04}", i);
     }
+    Ok(())
+}
+
+#[test]
+fn seeded_cases_repeat() {
+    let ruleset = Ruleset::default();
+    assert!(generate_case(2, 7, &ruleset).is_some());
+    assert_eq!(generate_case(2, 7, &ruleset), generate_case(2, 7, &ruleset));
+    // Islands are not used for other inventories, the shift limit keeps the search shallow
+    let uneven = Ruleset {
+        end_condition: ballcube::EndCondition::ShiftLimit(8),
+        gold_gate_types: [0, 0, 0, 1, 2, 3],
+        ..Ruleset::default()
+    };
+    assert!(generate_case(2, 7, &uneven).is_some());
+    assert_eq!(generate_case(2, 7, &uneven), generate_case(2, 7, &uneven));
+
+    let over_at_once = Ruleset {
+        end_condition: ballcube::EndCondition::BallsOut(0),
+        ..Ruleset::default()
+    };
+    assert_eq!(generate_case(14, 7, &over_at_once), None);
 }
 
 #[test]
@@ -1,8 +1,10 @@
 use ballcube::{Move, Player};
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MoveChain {
-    chain: Vec<Move>,
+    /// Turns from the last to the first, `None` for a turn in which the player had no moves and passed
+    chain: Vec<Option<Move>>,
     starting_player: Player,
 }
 impl MoveChain {
@@ -14,15 +16,28 @@ impl MoveChain {
     }
 
     pub fn prepend(&mut self, m: Move) {
-        self.chain.push(m);
+        self.chain.push(Some(m));
         self.starting_player = self.starting_player.other();
     }
 
+    /// Prepends a turn in which the player on the move has no moves and passes
+    pub fn pass(&mut self) {
+        self.chain.push(None);
+        self.starting_player = self.starting_player.other();
+    }
+
+    /// All turns including passes, from the last to the first
     #[must_use]
-    pub fn moves(&self) -> &Vec<Move> {
+    pub fn turns(&self) -> &[Option<Move>] {
         &self.chain
     }
 
+    /// Moves without the passes, from the last to the first
+    #[must_use]
+    pub fn moves(&self) -> Vec<Move> {
+        self.chain.iter().flatten().copied().collect()
+    }
+
     #[must_use]
     pub fn starting_player(&self) -> Player {
         self.starting_player